@@ -7,12 +7,11 @@ function handleLinkClick(el) {
     if ((matched = href.match(/^entry:\/\/([^#]*)(#.*)?$/))) {
         // todo redirect with hash
         if (matched[1]) {
-            var url = new URL(location.href);
-            window.location.href =
-                '/@entry?dict_id=' +
-                url.searchParams.get('dict_id') +
-                '&name=' +
-                encodeURIComponent(matched[1]);
+            // keep other params like dark and scale
+            var params = new URL(location.href).searchParams;
+            params.set('name', matched[1]);
+            params.set('nav', 'link');
+            window.location.href = '/@entry?' + params.toString();
         }
         if (!matched[1] && matched[2]) {
             location.hash = matched[2];
@@ -0,0 +1,139 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use axum::body::Bytes;
+use serde::Serialize;
+use tokio::{fs, sync::Mutex};
+
+struct CacheItem {
+    content: Bytes,
+    modified: SystemTime,
+    last_used: u64,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct AssetCacheStats {
+    pub capacity: usize,
+    pub size: usize,
+    pub count: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// A LRU cache of files bounded by the total size of their content. An item
+/// is stale once the modification time of its file changes.
+pub struct AssetCache {
+    capacity: usize,
+    max_item_size: usize,
+    items: HashMap<PathBuf, CacheItem>,
+    /// Items ordered by their last use, the first is evicted first
+    order: BTreeMap<u64, PathBuf>,
+    tick: u64,
+    stats: AssetCacheStats,
+}
+
+impl AssetCache {
+    pub fn new(capacity: usize, max_item_size: usize) -> Self {
+        Self {
+            capacity,
+            max_item_size: max_item_size.min(capacity),
+            items: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            stats: AssetCacheStats {
+                capacity,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn get(&mut self, file: &Path, modified: SystemTime) -> Option<Bytes> {
+        if self.items.get(file).is_some_and(|x| x.modified != modified) {
+            self.remove(file);
+        }
+        let item = match self.items.get_mut(file) {
+            Some(v) => v,
+            None => {
+                self.stats.misses += 1;
+                return None;
+            }
+        };
+        self.tick += 1;
+        self.order.remove(&item.last_used);
+        item.last_used = self.tick;
+        self.order.insert(self.tick, file.to_path_buf());
+        self.stats.hits += 1;
+        Some(item.content.clone())
+    }
+
+    fn insert(&mut self, file: PathBuf, modified: SystemTime, content: Bytes) {
+        if content.len() > self.max_item_size {
+            return;
+        }
+        self.remove(&file);
+        while self.stats.size + content.len() > self.capacity {
+            let (_, oldest) = match self.order.pop_first() {
+                Some(v) => v,
+                None => break,
+            };
+            if let Some(v) = self.items.remove(&oldest) {
+                self.stats.size -= v.content.len();
+                self.stats.evictions += 1;
+            }
+        }
+        self.tick += 1;
+        self.stats.size += content.len();
+        self.order.insert(self.tick, file.clone());
+        self.items.insert(
+            file,
+            CacheItem {
+                content,
+                modified,
+                last_used: self.tick,
+            },
+        );
+        self.stats.count = self.items.len();
+    }
+
+    fn remove(&mut self, file: &Path) {
+        if let Some(v) = self.items.remove(file) {
+            self.order.remove(&v.last_used);
+            self.stats.size -= v.content.len();
+            self.stats.count = self.items.len();
+        }
+    }
+
+    pub fn stats(&self) -> AssetCacheStats {
+        self.stats.clone()
+    }
+}
+
+/// Reads a file through the cache. The file is read from disk again if it has
+/// been modified since it was cached.
+pub async fn read_file(cache: &Mutex<AssetCache>, file: &Path) -> io::Result<Bytes> {
+    let meta = fs::metadata(file).await?;
+    if !meta.is_file() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "not a file"));
+    }
+    // Files without a modification time are not cached as they can't be
+    // invalidated.
+    let modified = meta.modified().ok();
+    if let Some(modified) = modified {
+        if let Some(v) = cache.lock().await.get(file, modified) {
+            return Ok(v);
+        }
+    }
+    let content = Bytes::from(fs::read(file).await?);
+    if let Some(modified) = modified {
+        cache
+            .lock()
+            .await
+            .insert(file.to_path_buf(), modified, content.clone());
+    }
+    Ok(content)
+}
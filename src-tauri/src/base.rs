@@ -5,14 +5,6 @@ use std::path::PathBuf;
 use std::{collections::HashMap, fs, path::Path, sync::Arc};
 use tauri::{AppHandle, Manager};
 use tokio::sync::{Mutex, RwLock};
-#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
-use {
-    log::{debug, error},
-    mouse_position::mouse_position::Mouse,
-    ocrs::{ImageSource, OcrEngine},
-    rten_imageproc::PointF,
-    xcap::image::{DynamicImage, GenericImageView},
-};
 
 use crate::settings::{DictItem, Settings};
 
@@ -24,7 +16,7 @@ use beluga_core::{
 pub struct AppState {
     last_dict_id: Arc<Mutex<u32>>,
     last_cache_id: Arc<Mutex<u32>>,
-    dicts: Arc<RwLock<HashMap<u32, Arc<Mutex<Dictionary>>>>>,
+    pub dicts: Arc<RwLock<HashMap<u32, Arc<Mutex<Dictionary>>>>>,
     pub cache: Arc<RwLock<NodeCache>>,
     pub settings: Arc<RwLock<Settings>>,
 }
@@ -96,6 +88,7 @@ impl AppState {
                     id: *id,
                     name: name.clone(),
                     available: true,
+                    trusted: false,
                 });
             }
         }
@@ -126,6 +119,18 @@ impl AppState {
     }
 }
 
+pub static APP_IDENTIFIER: &str = "com.youginil.beluga";
+
+/// Same location as `app_config_dir()`, for callers without an `AppHandle`.
+pub fn default_config_directory() -> Option<PathBuf> {
+    dirs::config_dir().map(|v| v.join(APP_IDENTIFIER))
+}
+
+/// Same location as `app_data_dir()`, for callers without an `AppHandle`.
+pub fn default_data_directory() -> Option<PathBuf> {
+    dirs::data_dir().map(|v| v.join(APP_IDENTIFIER))
+}
+
 pub fn get_resource_directory(ah: AppHandle) -> PathBuf {
     #[cfg(debug_assertions)]
     let dir = ah.path().resource_dir().unwrap().join("../../resources");
@@ -134,6 +139,20 @@ pub fn get_resource_directory(ah: AppHandle) -> PathBuf {
     dir
 }
 
+/// Looks for the bundled resources next to the executable, for running
+/// without an `AppHandle`.
+pub fn guess_resource_directory() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+    let candidates = [
+        exe_dir.join("resources"),
+        exe_dir.join("../Resources/resources"),
+        exe_dir.join("../lib/Beluga/resources"),
+        exe_dir.join("../../resources"),
+    ];
+    candidates.into_iter().find(|x| x.is_dir())
+}
+
 #[derive(Serialize)]
 pub struct Pagination<T> {
     pub page: u32,
@@ -142,97 +161,3 @@ pub struct Pagination<T> {
     pub total: u32,
     pub list: Vec<T>,
 }
-
-#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
-pub fn recognize_text(engine: &OcrEngine, w: u32, h: u32) -> String {
-    let w = w as i32;
-    let h = h as i32;
-    let (x, y) = match Mouse::get_mouse_position() {
-        Mouse::Position { x, y } => (x, y),
-        Mouse::Error => return "".to_string(),
-    };
-    debug!("Mouse position: {} {} {} {}", x, y, w, h);
-    let monitors = if let Ok(v) = xcap::Monitor::all() {
-        v
-    } else {
-        return "".to_string();
-    };
-    for monitor in monitors {
-        let mx = match monitor.x() {
-            Ok(v) => v,
-            Err(e) => {
-                error!("fail to get monitor x. {}", e);
-                return "".to_string();
-            }
-        };
-        let my = match monitor.y() {
-            Ok(v) => v,
-            Err(e) => {
-                error!("fail to get monitor y. {}", e);
-                return "".to_string();
-            }
-        };
-        let mw = match monitor.width() {
-            Ok(v) => v as i32,
-            Err(e) => {
-                error!("fail to get monitor width. {}", e);
-                return "".to_string();
-            }
-        };
-        let mh = match monitor.height() {
-            Ok(v) => v as i32,
-            Err(e) => {
-                error!("fail to get monitor height. {}", e);
-                return "".to_string();
-            }
-        };
-        debug!("Screen: {} {}, {} {}", mx, my, mw, mh);
-        if x >= mx && x <= mx + mw && y >= my && y <= my + mh {
-            if let Ok(img) = monitor.capture_image() {
-                let x1 = std::cmp::max(x - w / 2, 0) as u32;
-                let y1 = std::cmp::max(y - h / 2, 0) as u32;
-                let x2 = std::cmp::min(mx + mw, x + w / 2) as u32;
-                let y2 = std::cmp::min(my + mh, y + h / 2) as u32;
-                let img2 = DynamicImage::from(img).crop(x1, y1, x2 - x1, y2 - y1);
-                // let mut file = std::fs::File::create("/Users/jiaju/Downloads/a.png").unwrap();
-                // img2.write_to(&mut file, xcap::image::ImageFormat::Png)
-                //     .unwrap();
-                let img3 = match ImageSource::from_bytes(img2.as_bytes(), img2.dimensions()) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        error!("fail to new image source from bytes. {}", e);
-                        return "".to_string();
-                    }
-                };
-                let ocr_input = match engine.prepare_input(img3) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        error!("fail to prepare input. {}", e);
-                        return "".to_string();
-                    }
-                };
-                let word_rects = match engine.detect_words(&ocr_input) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        error!("fail to detect words. {}", e);
-                        return "".to_string();
-                    }
-                };
-                let words = word_rects.iter().map(|x| vec![*x]).collect::<Vec<Vec<_>>>();
-                if let Ok(wds) = engine.recognize_text(&ocr_input, &words) {
-                    let x0 = x as f32 - x1 as f32;
-                    let y0 = y as f32 - y1 as f32;
-                    for (i, wd) in wds.iter().enumerate() {
-                        if let Some(v) = wd {
-                            debug!("{:?} {}", word_rects[i], v);
-                            if word_rects[i].contains(PointF::from_yx(y0, x0)) {
-                                return format!("{}", v);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    "".to_string()
-}
@@ -0,0 +1,4 @@
+#[tokio::main]
+async fn main() {
+    beluga_lib::cli::run().await;
+}
@@ -0,0 +1,496 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
+use beluga_core::dictionary::{Dictionary, NodeCache};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use tokio::sync::{watch, RwLock};
+
+use crate::{
+    base::{default_config_directory, default_data_directory, guess_resource_directory, AppState},
+    dict_server::start_dict_server,
+    entry, export, html,
+    server::{bind_server, start_server, SERVER_PORT_FILE},
+    settings::{DictItem, Settings},
+};
+
+#[derive(Parser)]
+#[command(
+    name = "beluga-cli",
+    version,
+    about = "Beluga dictionary on the command line"
+)]
+struct Cli {
+    /// Directory of settings.json, defaults to the one used by the app
+    #[arg(long, global = true)]
+    config_dir: Option<PathBuf>,
+    /// Directory of app data, defaults to the one used by the app
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List dictionaries
+    Dicts {
+        #[arg(short, long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Look up the entry of a word
+    Lookup {
+        word: String,
+        /// Dictionary names, defaults to all enabled dictionaries
+        #[arg(short, long)]
+        dict: Vec<String>,
+        #[arg(short, long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// List words matching a prefix
+    Search {
+        prefix: String,
+        /// Dictionary names, defaults to all enabled dictionaries
+        #[arg(short, long)]
+        dict: Vec<String>,
+        /// Defaults to the prefix limit in settings
+        #[arg(short, long)]
+        limit: Option<usize>,
+        /// Defaults to the phrase limit in settings
+        #[arg(short, long)]
+        phrase_limit: Option<usize>,
+        #[arg(short, long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Extract a resource (image, audio, ...) of a dictionary
+    Resource {
+        name: String,
+        #[arg(short, long)]
+        dict: String,
+        /// Output file, defaults to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Export resources or entries of a dictionary to disk
+    Export {
+        #[arg(short, long)]
+        dict: String,
+        #[command(subcommand)]
+        target: ExportTarget,
+    },
+    /// Run the HTTP server without the GUI
+    Serve {
+        /// Defaults to the port in settings, or the first available port
+        /// from 19000
+        #[arg(short, long)]
+        port: Option<u16>,
+        /// Directory of entry.js, defaults to the resources next to the executable
+        #[arg(long)]
+        resource_dir: Option<PathBuf>,
+        /// Also serve the DICT protocol on this port, defaults to the settings
+        #[arg(long)]
+        dict_port: Option<u16>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportTarget {
+    /// A resource by name, keeping its folders
+    Resource {
+        name: String,
+        /// Output folder
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
+    /// Resources matching a glob like "*.mp3", among those referenced by entries
+    Resources {
+        pattern: String,
+        /// Output folder
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+        /// File of headwords to search, one per line, defaults to all
+        #[arg(short, long)]
+        words: Option<PathBuf>,
+    },
+    /// Entries as JSON lines if output ends with .jsonl, otherwise as HTML
+    /// files in the output folder
+    Entries {
+        #[arg(short, long)]
+        output: PathBuf,
+        /// File of headwords to export, one per line, defaults to all
+        #[arg(short, long)]
+        words: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Html,
+    Json,
+}
+
+#[derive(Serialize)]
+struct EntryOutput {
+    dict_id: u32,
+    dict: String,
+    word: String,
+    html: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct SearchOutput {
+    dict_id: u32,
+    dict: String,
+    words: Vec<String>,
+}
+
+pub async fn run() {
+    let cli = Cli::parse();
+    if let Err(e) = execute(cli).await {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn execute(cli: Cli) -> Result<()> {
+    let data_dir = match cli.data_dir {
+        Some(v) => v,
+        None => default_data_directory().ok_or_else(|| anyhow!("no data directory"))?,
+    };
+    let state = init_state(cli.config_dir.as_deref(), &data_dir).await?;
+    match cli.command {
+        Command::Dicts { format } => {
+            let settings_lock = state.settings.read().await;
+            let dicts = settings_lock.config.dicts.clone();
+            drop(settings_lock);
+            match format {
+                Format::Json => print_json(&dicts)?,
+                Format::Text | Format::Html => {
+                    for item in dicts {
+                        let flag = if item.available { "*" } else { " " };
+                        println!("{} {}", flag, item.name);
+                    }
+                }
+            }
+        }
+        Command::Lookup { word, dict, format } => {
+            let mut list: Vec<EntryOutput> = vec![];
+            for item in select_dicts(&state, &dict).await? {
+                let dictionary = if let Some(v) = state.get_dictionary(item.id).await {
+                    v
+                } else {
+                    continue;
+                };
+                let mut dict_lock = dictionary.lock().await;
+                for v in entry::search_entries(&mut dict_lock, state.cache.clone(), &word).await {
+                    list.push(EntryOutput {
+                        dict_id: item.id,
+                        dict: item.name.clone(),
+                        word: v.name,
+                        text: html::to_text(&v.content),
+                        html: v.content,
+                    });
+                }
+            }
+            if list.is_empty() && !matches!(format, Format::Json) {
+                return Err(anyhow!("no entry found for \"{}\"", word));
+            }
+            match format {
+                Format::Json => print_json(&list)?,
+                Format::Html => {
+                    for item in list {
+                        println!("<!-- {} -->\n{}", item.dict, item.html);
+                    }
+                }
+                Format::Text => {
+                    for (i, item) in list.iter().enumerate() {
+                        if i > 0 {
+                            println!();
+                        }
+                        println!("[{}]\n{}", item.dict, item.text);
+                    }
+                }
+            }
+        }
+        Command::Search {
+            prefix,
+            dict,
+            limit,
+            phrase_limit,
+            format,
+        } => {
+            let settings_lock = state.settings.read().await;
+            let limit = limit.unwrap_or(settings_lock.config.prefix_limit as usize);
+            let phrase_limit = phrase_limit.unwrap_or(settings_lock.config.phrase_limit as usize);
+            drop(settings_lock);
+            let mut list: Vec<SearchOutput> = vec![];
+            for item in select_dicts(&state, &dict).await? {
+                let dictionary = if let Some(v) = state.get_dictionary(item.id).await {
+                    v
+                } else {
+                    continue;
+                };
+                let mut dict_lock = dictionary.lock().await;
+                let words = dict_lock
+                    .search(state.cache.clone(), &prefix, false, limit, phrase_limit)
+                    .await;
+                list.push(SearchOutput {
+                    dict_id: item.id,
+                    dict: item.name.clone(),
+                    words,
+                });
+            }
+            match format {
+                Format::Json => print_json(&list)?,
+                Format::Text | Format::Html => {
+                    let mut words: Vec<&String> = vec![];
+                    for item in &list {
+                        for wd in &item.words {
+                            if !words.contains(&wd) {
+                                words.push(wd);
+                            }
+                        }
+                    }
+                    for wd in words {
+                        println!("{}", wd);
+                    }
+                }
+            }
+        }
+        Command::Resource { name, dict, output } => {
+            let item = select_dicts(&state, &[dict]).await?.remove(0);
+            let dictionary = state
+                .get_dictionary(item.id)
+                .await
+                .ok_or_else(|| anyhow!("dictionary \"{}\" is not loaded", item.name))?;
+            let mut dict_lock = dictionary.lock().await;
+            let data = dict_lock
+                .search_resource(state.cache.clone(), &name)
+                .await
+                .ok_or_else(|| anyhow!("no resource \"{}\" in \"{}\"", name, item.name))?;
+            match output {
+                Some(file) => std::fs::write(file, data)?,
+                None => std::io::stdout().write_all(&data)?,
+            }
+        }
+        Command::Export { dict, target } => {
+            let item = select_dicts(&state, &[dict]).await?.remove(0);
+            let dictionary = state
+                .get_dictionary(item.id)
+                .await
+                .ok_or_else(|| anyhow!("dictionary \"{}\" is not loaded", item.name))?;
+            let mut dict_lock = dictionary.lock().await;
+            let cache = state.cache.clone();
+            match target {
+                ExportTarget::Resource { name, output } => {
+                    let file =
+                        export::export_resource(&mut dict_lock, cache, &name, &output).await?;
+                    eprintln!("{}", file.display());
+                }
+                ExportTarget::Resources {
+                    pattern,
+                    output,
+                    words,
+                } => {
+                    let words = read_words(&mut dict_lock, cache.clone(), words.as_deref()).await?;
+                    let names =
+                        export::find_resources(&mut dict_lock, cache.clone(), &words, &pattern)
+                            .await;
+                    let mut count = 0;
+                    for name in &names {
+                        match export::export_resource(&mut dict_lock, cache.clone(), name, &output)
+                            .await
+                        {
+                            Ok(_) => count += 1,
+                            Err(e) => eprintln!("warning: {}", e),
+                        }
+                    }
+                    eprintln!("{} of {} resources exported", count, names.len());
+                }
+                ExportTarget::Entries { output, words } => {
+                    let words = read_words(&mut dict_lock, cache.clone(), words.as_deref()).await?;
+                    let count =
+                        export::export_entries(&mut dict_lock, cache, &words, &output).await?;
+                    eprintln!("{} of {} entries exported", count, words.len());
+                }
+            }
+        }
+        Command::Serve {
+            port,
+            resource_dir,
+            dict_port,
+        } => {
+            let resource_dir = match resource_dir.or_else(guess_resource_directory) {
+                Some(v) => v,
+                None => {
+                    eprintln!("warning: no resource directory, entry.js is not injected");
+                    PathBuf::new()
+                }
+            };
+            let settings_lock = state.settings.read().await;
+            let port = port.or(Some(settings_lock.config.http_port).filter(|x| *x != 0));
+            let dict_port = dict_port.or(if settings_lock.config.dict_server {
+                Some(settings_lock.config.dict_server_port)
+            } else {
+                None
+            });
+            drop(settings_lock);
+            serve(state, &data_dir, resource_dir, port, dict_port).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn serve(
+    state: AppState,
+    data_dir: &Path,
+    resource_dir: PathBuf,
+    port: Option<u16>,
+    dict_port: Option<u16>,
+) -> Result<()> {
+    std::fs::create_dir_all(data_dir)?;
+    let port_file = data_dir.join(SERVER_PORT_FILE);
+    let listener = bind_server(None, port).await?;
+    eprintln!("listening on port {}", listener.local_addr()?.port());
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        let _ = shutdown_tx.send(true);
+    });
+    let shutdown = |mut rx: watch::Receiver<bool>| async move {
+        let _ = rx.wait_for(|x| *x).await;
+    };
+    let dict_server = dict_port.map(|dict_port| {
+        eprintln!("DICT server on port {}", dict_port);
+        tokio::spawn(start_dict_server(
+            state.settings.clone(),
+            state.dicts.clone(),
+            state.cache.clone(),
+            dict_port,
+            shutdown(shutdown_rx.clone()),
+        ))
+    });
+    let server = tokio::spawn(start_server(
+        state.settings.clone(),
+        state.dicts.clone(),
+        state.cache.clone(),
+        resource_dir,
+        None,
+        listener,
+        Some(port_file),
+        shutdown(shutdown_rx),
+    ));
+    server.await??;
+    if let Some(v) = dict_server {
+        v.await??;
+    }
+    Ok(())
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("fail to listen for ctrl-c. {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut v) => {
+                v.recv().await;
+            }
+            Err(e) => {
+                eprintln!("fail to listen for SIGTERM. {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    eprintln!("shutting down");
+}
+
+async fn init_state(config_dir: Option<&Path>, data_dir: &Path) -> Result<AppState> {
+    let config_dir = match config_dir {
+        Some(v) => v.to_path_buf(),
+        None => default_config_directory().ok_or_else(|| anyhow!("no config directory"))?,
+    };
+    let settings = Settings::init(
+        config_dir
+            .to_str()
+            .ok_or_else(|| anyhow!("invalid config directory"))?,
+        data_dir
+            .to_str()
+            .ok_or_else(|| anyhow!("invalid data directory"))?,
+    )?;
+    let cache_size = settings.config.cache_size * 1024 * 1024;
+    let cache = Arc::new(RwLock::new(NodeCache::new(cache_size.into())));
+    let settings = Arc::new(RwLock::new(settings));
+    let dicts = Arc::new(RwLock::new(HashMap::new()));
+    let state = AppState::new(settings, dicts, cache);
+    state.load_dictionaries().await?;
+    Ok(state)
+}
+
+/// Returns the dictionaries with the given names in the configured order, or
+/// all enabled dictionaries if no name is given.
+async fn select_dicts(state: &AppState, names: &[String]) -> Result<Vec<DictItem>> {
+    let settings_lock = state.settings.read().await;
+    let dicts = &settings_lock.config.dicts;
+    if names.is_empty() {
+        return Ok(dicts.iter().filter(|x| x.available).cloned().collect());
+    }
+    let mut list = vec![];
+    for name in names {
+        match dicts.iter().find(|x| x.name.eq(name)) {
+            Some(v) => list.push(v.clone()),
+            None => return Err(anyhow!("no dictionary named \"{}\"", name)),
+        }
+    }
+    Ok(list)
+}
+
+/// Reads headwords from a file, one per line, or lists all headwords of the
+/// dictionary.
+async fn read_words(
+    dict: &mut Dictionary,
+    cache: Arc<RwLock<NodeCache>>,
+    file: Option<&Path>,
+) -> Result<BTreeSet<String>> {
+    match file {
+        Some(v) => Ok(std::fs::read_to_string(v)?
+            .lines()
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_string())
+            .collect()),
+        None => {
+            eprintln!("listing headwords...");
+            let headwords = export::list_headwords(dict, cache).await;
+            if !headwords.complete {
+                eprintln!(
+                    "warning: headwords are found by prefix search, some may be missing. \
+                     Pass a file of headwords with --words to be sure."
+                );
+            }
+            Ok(headwords.words)
+        }
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
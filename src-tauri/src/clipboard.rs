@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use log::{debug, error};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::{base::AppState, lookup::lookup};
+
+static WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Looks up the text on the clipboard.
+pub async fn lookup_clipboard(ah: &AppHandle) -> Result<()> {
+    let text = ah
+        .clipboard()
+        .read_text()
+        .map_err(|e| anyhow!("no text on the clipboard. {}", e))?;
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(anyhow!("no text on the clipboard"));
+    }
+    lookup(ah, text, None).await
+}
+
+/// Polls the clipboard and looks up newly copied text that passes the
+/// filters while watching is on. Text already on the clipboard when watching
+/// starts is not looked up.
+pub fn watch_clipboard(ah: AppHandle) {
+    tokio::spawn(async move {
+        let mut last: Option<String> = None;
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let (watch, max_length, scripts) = {
+                let state = ah.state::<AppState>();
+                let settings_lock = state.settings.read().await;
+                let config = &settings_lock.config;
+                (
+                    config.clipboard_watch,
+                    config.clipboard_max_length,
+                    config.clipboard_scripts.clone(),
+                )
+            };
+            if !watch {
+                last = None;
+                continue;
+            }
+            let text = match ah.clipboard().read_text() {
+                Ok(v) => v.trim().to_string(),
+                // Not text
+                Err(_) => continue,
+            };
+            let changed = last.as_ref().is_some_and(|x| *x != text);
+            let first = last.is_none();
+            last = Some(text.clone());
+            if first || !changed {
+                continue;
+            }
+            if !accept_text(&text, max_length as usize, &scripts) {
+                debug!("clipboard text is filtered out");
+                continue;
+            }
+            if let Err(e) = lookup(&ah, &text, None).await {
+                error!("fail to look up clipboard text. {:?}", e);
+            }
+        }
+    });
+}
+
+/// Whether copied text is looked up: not longer than `max_length` chars, 0
+/// for no limit, and with letters in `scripts` only, any if it's empty.
+fn accept_text(text: &str, max_length: usize, scripts: &[String]) -> bool {
+    if text.is_empty() || (max_length > 0 && text.chars().count() > max_length) {
+        return false;
+    }
+    let mut letters = text.chars().filter(|c| c.is_alphabetic()).peekable();
+    if letters.peek().is_none() {
+        return false;
+    }
+    scripts.is_empty()
+        || letters.all(|c| script_of(c).is_some_and(|x| scripts.iter().any(|s| s == x)))
+}
+
+/// Script of a letter, one of `CLIPBOARD_SCRIPTS`
+fn script_of(c: char) -> Option<&'static str> {
+    let script = match c as u32 {
+        0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F | 0x1E00..=0x1EFF => "latin",
+        0x370..=0x3FF | 0x1F00..=0x1FFF => "greek",
+        0x400..=0x52F => "cyrillic",
+        0x590..=0x5FF => "hebrew",
+        0x600..=0x6FF | 0x750..=0x77F => "arabic",
+        0xE00..=0xE7F => "thai",
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => "hangul",
+        0x3040..=0x30FF => "kana",
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F => "han",
+        _ => return None,
+    };
+    Some(script)
+}
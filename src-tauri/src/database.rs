@@ -3,7 +3,7 @@ use std::{fs, path::Path};
 use log::{debug, error};
 use sqlx::{sqlite::SqlitePoolOptions, Executor, Row, SqliteConnection, SqlitePool};
 
-use crate::model::{book::BOOK_TABLE, word::WORD_TABLE};
+use crate::model::{book::BOOK_TABLE, history::HISTORY_TABLE, word::WORD_TABLE};
 
 const DB_FILE: &str = "data.db";
 
@@ -89,6 +89,31 @@ impl Database {
             ]);
             version = 3;
         }
+        if version == 3 {
+            sqls.extend_from_slice(&vec![
+                format!(
+                    "CREATE TABLE {} (
+                id          INTEGER PRIMARY KEY AUTOINCREMENT,
+                dict        TEXT    NOT NULL,
+                name        TEXT    NOT NULL,
+                create_time INTEGER NOT NULL
+            )",
+                    HISTORY_TABLE
+                ),
+                format!(
+                    "CREATE INDEX history_create_time ON {} (create_time)",
+                    HISTORY_TABLE
+                ),
+            ]);
+            version = 4;
+        }
+        if version == 4 {
+            sqls.extend_from_slice(&vec![format!(
+                "ALTER TABLE {} ADD COLUMN \"context\" TEXT NOT NULL DEFAULT ''",
+                WORD_TABLE
+            )]);
+            version = 5;
+        }
         for sql in &sqls {
             sqlx::query(sql.as_str())
                 .execute(&mut *tx)
@@ -0,0 +1,176 @@
+use log::{debug, error};
+use tauri::{AppHandle, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::lookup::{lookup, show_main_window, LookupRequest};
+
+/// Other apps look up words with `beluga://lookup?word=...&dict=...`
+pub static DEEP_LINK_SCHEME: &str = "beluga";
+static LOOKUP_HOST: &str = "lookup";
+
+/// Parses `--lookup word` and the optional `--dict name` of the command line.
+/// A flag followed by another flag has no value, use `--lookup=--x` to look
+/// up a word starting with `--`.
+pub fn parse_args<S: AsRef<str>>(args: &[S]) -> Option<LookupRequest> {
+    let mut text = None;
+    let mut dict = None;
+    let mut iter = args.iter().map(|x| x.as_ref()).peekable();
+    while let Some(arg) = iter.next() {
+        if arg == "--lookup" {
+            text = iter
+                .next_if(|x| !x.starts_with("--"))
+                .map(|x| x.to_string());
+        } else if let Some(v) = arg.strip_prefix("--lookup=") {
+            text = Some(v.to_string());
+        } else if arg == "--dict" {
+            dict = iter
+                .next_if(|x| !x.starts_with("--"))
+                .map(|x| x.to_string());
+        } else if let Some(v) = arg.strip_prefix("--dict=") {
+            dict = Some(v.to_string());
+        }
+    }
+    let text = text.map(|x| x.trim().to_string())?;
+    if text.is_empty() {
+        return None;
+    }
+    Some(LookupRequest {
+        text,
+        dict: dict.filter(|x| !x.is_empty()),
+    })
+}
+
+/// Parses a `beluga://lookup?word=...&dict=...` link.
+pub fn parse_url(url: &Url) -> Option<LookupRequest> {
+    if url.scheme() != DEEP_LINK_SCHEME || url.host_str() != Some(LOOKUP_HOST) {
+        return None;
+    }
+    let mut text = None;
+    let mut dict = None;
+    for (k, v) in url.query_pairs() {
+        match k.as_ref() {
+            "word" => text = Some(v.trim().to_string()),
+            "dict" if !v.is_empty() => dict = Some(v.to_string()),
+            _ => {}
+        }
+    }
+    text.filter(|x| !x.is_empty())
+        .map(|text| LookupRequest { text, dict })
+}
+
+/// The lookup the app is launched with, from the command line or a link.
+pub fn launch_lookup(ah: &AppHandle) -> Option<LookupRequest> {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(v) = parse_args(&args) {
+        return Some(v);
+    }
+    match ah.deep_link().get_current() {
+        Ok(urls) => urls?.iter().find_map(parse_url),
+        Err(e) => {
+            error!("fail to get deep link. {}", e);
+            None
+        }
+    }
+}
+
+/// Looks up the words of links opened while the app is running.
+pub fn handle_open_urls(ah: &AppHandle, urls: Vec<Url>) {
+    debug!("open urls: {:?}", urls);
+    match urls.iter().find_map(parse_url) {
+        Some(req) => run_lookup(ah, req),
+        None => error!("invalid deep link: {:?}", urls),
+    }
+}
+
+/// Handles the arguments of the app launched again. Links are handled by
+/// the deep link plugin, otherwise the running app is brought to front.
+pub fn handle_second_instance(ah: &AppHandle, args: Vec<String>) {
+    debug!("second instance: {:?}", args);
+    if let Some(req) = parse_args(&args) {
+        run_lookup(ah, req);
+        return;
+    }
+    let scheme = format!("{}:", DEEP_LINK_SCHEME);
+    if args.iter().skip(1).any(|x| x.starts_with(&scheme)) {
+        return;
+    }
+    if let Err(e) = show_main_window(ah) {
+        error!("fail to show main window. {:?}", e);
+    }
+}
+
+fn run_lookup(ah: &AppHandle, req: LookupRequest) {
+    let ah = ah.clone();
+    tokio::spawn(async move {
+        if let Err(e) = lookup(&ah, &req.text, req.dict.as_deref()).await {
+            error!("fail to look up {}. {:?}", req.text, e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Option<(String, Option<String>)> {
+        parse_args(list).map(|x| (x.text, x.dict))
+    }
+
+    fn url(s: &str) -> Option<(String, Option<String>)> {
+        parse_url(&Url::parse(s).unwrap()).map(|x| (x.text, x.dict))
+    }
+
+    #[test]
+    fn parse_lookup_args() {
+        assert_eq!(
+            args(&["beluga", "--lookup", "apple", "--dict", "WordNet"]),
+            Some(("apple".into(), Some("WordNet".into())))
+        );
+        assert_eq!(
+            args(&["beluga", "--dict=WordNet", "--lookup= ice cream "]),
+            Some(("ice cream".into(), Some("WordNet".into())))
+        );
+        assert_eq!(
+            args(&["beluga", "--lookup", "apple", "--dict"]),
+            Some(("apple".into(), None))
+        );
+        assert_eq!(
+            args(&["beluga", "--lookup=apple", "--dict="]),
+            Some(("apple".into(), None))
+        );
+        assert_eq!(args(&["beluga", "--lookup"]), None);
+        assert_eq!(args(&["beluga", "--lookup", "--dict", "WordNet"]), None);
+        assert_eq!(
+            args(&["beluga", "--lookup", "apple", "--dict", "--lookup", "pear"]),
+            Some(("pear".into(), None))
+        );
+        assert_eq!(
+            args(&["beluga", "--lookup=--help"]),
+            Some(("--help".into(), None))
+        );
+        assert_eq!(args(&["beluga", "--lookup", "  "]), None);
+        assert_eq!(args(&["beluga", "--dict", "WordNet"]), None);
+        assert_eq!(args(&["beluga"]), None);
+    }
+
+    #[test]
+    fn parse_lookup_urls() {
+        assert_eq!(
+            url("beluga://lookup?word=ice%20cream&dict=A%26B"),
+            Some(("ice cream".into(), Some("A&B".into())))
+        );
+        assert_eq!(
+            url("beluga://lookup?word=caf%C3%A9+au+lait"),
+            Some(("café au lait".into(), None))
+        );
+        assert_eq!(
+            url("beluga://lookup?dict=&word=apple"),
+            Some(("apple".into(), None))
+        );
+        assert_eq!(url("beluga://search?word=apple"), None);
+        assert_eq!(url("beluga://?word=apple"), None);
+        assert_eq!(url("https://lookup?word=apple"), None);
+        assert_eq!(url("beluga://lookup?dict=WordNet"), None);
+        assert_eq!(url("beluga://lookup?word=%20"), None);
+    }
+}
@@ -0,0 +1,655 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::{anyhow, Result};
+use beluga_core::dictionary::{Dictionary, NodeCache};
+use log::{debug, info, warn};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{Mutex, RwLock},
+};
+
+use crate::{entry, export, html, settings::Settings};
+
+pub static DEFAULT_DICT_SERVER_PORT: u16 = 2628;
+
+static SERVER_NAME: &str = "beluga";
+static DEFAULT_STRATEGY: &str = "prefix";
+static STRATEGIES: [(&str, &str); 5] = [
+    ("exact", "Match headwords exactly"),
+    ("prefix", "Match prefixes"),
+    (
+        "substring",
+        "Match substring occurring anywhere in a headword",
+    ),
+    ("soundex", "Match using SOUNDEX algorithm"),
+    ("lev", "Match headwords within Levenshtein distance one"),
+];
+static MATCH_LIMIT: usize = 100;
+/// Longest command line in bytes including CRLF (RFC 2229 3.2)
+static MAX_LINE_LENGTH: usize = 1024;
+
+static CONNECTION_ID: AtomicU32 = AtomicU32::new(1);
+
+#[derive(Clone)]
+struct ServerState {
+    dicts: Arc<RwLock<HashMap<u32, Arc<Mutex<Dictionary>>>>>,
+    cache: Arc<RwLock<NodeCache>>,
+    settings: Arc<RwLock<Settings>>,
+}
+
+/// A database as seen by DICT clients. Names can't contain spaces, so they
+/// are replaced with underscores.
+struct Database {
+    name: String,
+    description: String,
+    dict: Arc<Mutex<Dictionary>>,
+}
+
+/// Serves the loaded dictionaries over the DICT protocol (RFC 2229) on
+/// localhost.
+pub async fn start_dict_server<F>(
+    settings: Arc<RwLock<Settings>>,
+    dicts: Arc<RwLock<HashMap<u32, Arc<Mutex<Dictionary>>>>>,
+    cache: Arc<RwLock<NodeCache>>,
+    port: u16,
+    shutdown: F,
+) -> Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let state = ServerState {
+        dicts,
+        cache,
+        settings,
+    };
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
+    info!("DICT server listening on port {}", port);
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            r = listener.accept() => {
+                let (stream, addr) = match r {
+                    Ok(v) => v,
+                    Err(e) => {
+                        warn!("fail to accept DICT connection. {}", e);
+                        continue;
+                    }
+                };
+                debug!("DICT connection from {}", addr);
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(state, stream).await {
+                        debug!("DICT connection closed. {}", e);
+                    }
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_connection(state: ServerState, stream: TcpStream) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let msg_id = format!(
+        "<{}.{}@{}>",
+        std::process::id(),
+        CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+        SERVER_NAME
+    );
+    write_line(
+        &mut writer,
+        &format!("220 {} Beluga DICT server <mime> {}", SERVER_NAME, msg_id),
+    )
+    .await?;
+    let mut mime = false;
+    while let Some(line) = read_line(&mut reader).await? {
+        let args = parse_command(&line);
+        if args.is_empty() {
+            continue;
+        }
+        let command = args[0].to_ascii_uppercase();
+        match command.as_str() {
+            "DEFINE" => {
+                if args.len() != 3 {
+                    write_line(&mut writer, "501 Syntax error, illegal parameters").await?;
+                    continue;
+                }
+                define(&state, &mut writer, &args[1], &args[2], mime).await?;
+            }
+            "MATCH" => {
+                if args.len() != 4 {
+                    write_line(&mut writer, "501 Syntax error, illegal parameters").await?;
+                    continue;
+                }
+                match_words(&state, &mut writer, &args[1], &args[2], &args[3]).await?;
+            }
+            "SHOW" => {
+                let sub = args
+                    .get(1)
+                    .map(|x| x.to_ascii_uppercase())
+                    .unwrap_or_default();
+                match sub.as_str() {
+                    "DB" | "DATABASES" => show_databases(&state, &mut writer).await?,
+                    "STRAT" | "STRATEGIES" => show_strategies(&mut writer).await?,
+                    "INFO" if args.len() == 3 => show_info(&state, &mut writer, &args[2]).await?,
+                    "SERVER" => {
+                        write_line(&mut writer, "114 server information follows").await?;
+                        write_text(
+                            &mut writer,
+                            &format!("Beluga {}", env!("CARGO_PKG_VERSION")),
+                        )
+                        .await?;
+                        write_line(&mut writer, "250 ok").await?;
+                    }
+                    _ => write_line(&mut writer, "501 Syntax error, illegal parameters").await?,
+                }
+            }
+            "OPTION" => {
+                if args.len() == 2 && args[1].eq_ignore_ascii_case("MIME") {
+                    mime = true;
+                    write_line(&mut writer, "250 ok - using MIME headers").await?;
+                } else {
+                    write_line(&mut writer, "501 Syntax error, illegal parameters").await?;
+                }
+            }
+            "CLIENT" => write_line(&mut writer, "250 ok").await?,
+            "STATUS" => write_line(&mut writer, "210 status ok").await?,
+            "HELP" => {
+                write_line(&mut writer, "113 help text follows").await?;
+                write_text(
+                    &mut writer,
+                    "DEFINE database word\n\
+                     MATCH database strategy word\n\
+                     SHOW DB\n\
+                     SHOW STRAT\n\
+                     SHOW INFO database\n\
+                     SHOW SERVER\n\
+                     OPTION MIME\n\
+                     CLIENT info\n\
+                     STATUS\n\
+                     HELP\n\
+                     QUIT",
+                )
+                .await?;
+                write_line(&mut writer, "250 ok").await?;
+            }
+            "QUIT" => {
+                write_line(&mut writer, "221 bye").await?;
+                break;
+            }
+            "AUTH" | "SASLAUTH" | "SASLRESP" => {
+                write_line(&mut writer, "502 Command not implemented").await?
+            }
+            _ => write_line(&mut writer, "500 Syntax error, command not recognized").await?,
+        }
+    }
+    Ok(())
+}
+
+async fn define<W: AsyncWrite + Unpin>(
+    state: &ServerState,
+    writer: &mut W,
+    db: &str,
+    word: &str,
+    mime: bool,
+) -> Result<()> {
+    let databases = match select_databases(state, db).await {
+        Some(v) => v,
+        None => return write_line(writer, "550 Invalid database, use \"SHOW DB\" for list").await,
+    };
+    let mut definitions: Vec<(&Database, String)> = vec![];
+    for item in &databases {
+        let mut dict_lock = item.dict.lock().await;
+        let entries = entry::search_entries(&mut dict_lock, state.cache.clone(), word).await;
+        if entries.is_empty() {
+            continue;
+        }
+        for v in entries {
+            definitions.push((item, html::to_text(&v.content)));
+        }
+        if db == "!" {
+            break;
+        }
+    }
+    if definitions.is_empty() {
+        return write_line(writer, "552 no match").await;
+    }
+    write_line(
+        writer,
+        &format!("150 {} definitions retrieved", definitions.len()),
+    )
+    .await?;
+    for (item, text) in definitions {
+        write_line(
+            writer,
+            &format!(
+                "151 {} {} {}",
+                quote(word),
+                item.name,
+                quote(&item.description)
+            ),
+        )
+        .await?;
+        if mime {
+            write_line(writer, "Content-type: text/plain; charset=utf-8").await?;
+            write_line(writer, "Content-transfer-encoding: 8bit").await?;
+            write_line(writer, "").await?;
+        }
+        write_text(writer, &text).await?;
+    }
+    write_line(writer, "250 ok").await
+}
+
+async fn match_words<W: AsyncWrite + Unpin>(
+    state: &ServerState,
+    writer: &mut W,
+    db: &str,
+    strategy: &str,
+    word: &str,
+) -> Result<()> {
+    let databases = match select_databases(state, db).await {
+        Some(v) => v,
+        None => return write_line(writer, "550 Invalid database, use \"SHOW DB\" for list").await,
+    };
+    let strategy = if strategy == "." {
+        DEFAULT_STRATEGY.to_string()
+    } else {
+        strategy.to_ascii_lowercase()
+    };
+    if !STRATEGIES.iter().any(|(x, _)| *x == strategy) {
+        return write_line(
+            writer,
+            "551 Invalid strategy, use \"SHOW STRAT\" for a list",
+        )
+        .await;
+    }
+    let mut matches: Vec<(&str, String)> = vec![];
+    for item in &databases {
+        let mut dict_lock = item.dict.lock().await;
+        let words = find_matches(&mut dict_lock, state.cache.clone(), &strategy, word).await;
+        if words.is_empty() {
+            continue;
+        }
+        for wd in words {
+            matches.push((&item.name, wd));
+        }
+        if db == "!" {
+            break;
+        }
+    }
+    if matches.is_empty() {
+        return write_line(writer, "552 no match").await;
+    }
+    write_line(writer, &format!("152 {} matches found", matches.len())).await?;
+    let text = matches
+        .iter()
+        .map(|(db, wd)| format!("{} {}", db, quote(wd)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    write_text(writer, &text).await?;
+    write_line(writer, "250 ok").await
+}
+
+async fn find_matches(
+    dict: &mut Dictionary,
+    cache: Arc<RwLock<NodeCache>>,
+    strategy: &str,
+    word: &str,
+) -> Vec<String> {
+    let lower = word.to_lowercase();
+    let mut result: Vec<String> = vec![];
+    match strategy {
+        "exact" => {
+            let list = dict
+                .search(cache.clone(), word, false, MATCH_LIMIT, 0)
+                .await;
+            result.extend(list.into_iter().filter(|x| x.to_lowercase() == lower));
+            if result.is_empty() && dict.search_entry(cache, word).await.is_some() {
+                result.push(word.to_string());
+            }
+        }
+        "prefix" => {
+            let list = dict.search(cache, word, false, MATCH_LIMIT, 0).await;
+            result.extend(
+                list.into_iter()
+                    .filter(|x| x.to_lowercase().starts_with(&lower)),
+            );
+        }
+        "substring" => {
+            // Every headword is listed, which takes a while on large
+            // dictionaries.
+            let words = export::list_headwords(dict, cache).await.words;
+            result = substring_matches(words, &lower);
+        }
+        "soundex" | "lev" => {
+            // Candidates are the neighbours of every prefix of the word, so
+            // only misspellings after the first letter can be found.
+            let chars: Vec<char> = word.chars().collect();
+            let code = soundex(word);
+            for i in (1..=chars.len()).rev() {
+                let prefix: String = chars[..i].iter().collect();
+                let list = dict
+                    .search(cache.clone(), &prefix, false, MATCH_LIMIT, 0)
+                    .await;
+                for wd in list {
+                    if result.contains(&wd) {
+                        continue;
+                    }
+                    let matched = if strategy == "soundex" {
+                        !code.is_empty() && soundex(&wd) == code
+                    } else {
+                        levenshtein(&wd.to_lowercase(), &lower) <= 1
+                    };
+                    if matched {
+                        result.push(wd);
+                    }
+                }
+                if result.len() >= MATCH_LIMIT {
+                    break;
+                }
+            }
+        }
+        _ => {}
+    }
+    result.dedup();
+    result
+}
+
+async fn show_databases<W: AsyncWrite + Unpin>(state: &ServerState, writer: &mut W) -> Result<()> {
+    let databases = select_databases(state, "*").await.unwrap_or_default();
+    if databases.is_empty() {
+        return write_line(writer, "554 No databases present").await;
+    }
+    write_line(
+        writer,
+        &format!("110 {} databases present", databases.len()),
+    )
+    .await?;
+    let text = databases
+        .iter()
+        .map(|x| format!("{} {}", x.name, quote(&x.description)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    write_text(writer, &text).await?;
+    write_line(writer, "250 ok").await
+}
+
+async fn show_strategies<W: AsyncWrite + Unpin>(writer: &mut W) -> Result<()> {
+    write_line(
+        writer,
+        &format!("111 {} strategies available", STRATEGIES.len()),
+    )
+    .await?;
+    let text = STRATEGIES
+        .iter()
+        .map(|(name, desc)| format!("{} {}", name, quote(desc)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    write_text(writer, &text).await?;
+    write_line(writer, "250 ok").await
+}
+
+async fn show_info<W: AsyncWrite + Unpin>(
+    state: &ServerState,
+    writer: &mut W,
+    db: &str,
+) -> Result<()> {
+    let databases = select_databases(state, "*").await.unwrap_or_default();
+    let item = match databases.iter().find(|x| x.name == db) {
+        Some(v) => v,
+        None => return write_line(writer, "550 Invalid database, use \"SHOW DB\" for list").await,
+    };
+    write_line(writer, &format!("112 information for {}", item.name)).await?;
+    write_text(writer, &item.description).await?;
+    write_line(writer, "250 ok").await
+}
+
+/// Returns enabled dictionaries for a database name, where `*` and `!` mean
+/// all of them. `None` means the database doesn't exist.
+async fn select_databases(state: &ServerState, db: &str) -> Option<Vec<Database>> {
+    let settings_lock = state.settings.read().await;
+    let dicts_lock = state.dicts.read().await;
+    let mut list = vec![];
+    for item in &settings_lock.config.dicts {
+        if !item.available {
+            continue;
+        }
+        let name = database_name(&item.name);
+        if db != "*" && db != "!" && db != name {
+            continue;
+        }
+        if let Some(dict) = dicts_lock.get(&item.id) {
+            list.push(Database {
+                name,
+                description: item.name.clone(),
+                dict: dict.clone(),
+            });
+        }
+    }
+    if list.is_empty() && db != "*" && db != "!" {
+        None
+    } else {
+        Some(list)
+    }
+}
+
+fn database_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join("_")
+}
+
+/// Splits a command line into words, honoring single and double quotes and
+/// backslash escapes.
+fn parse_command(line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.trim_end_matches(['\r', '\n']).chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+                in_word = true;
+            }
+            '"' | '\'' if quote.is_none() => {
+                quote = Some(c);
+                in_word = true;
+            }
+            _ if quote == Some(c) => quote = None,
+            _ if quote.is_none() && c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            _ => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        args.push(current);
+    }
+    args
+}
+
+/// Reads a line without the line break. The connection is closed on lines
+/// longer than `MAX_LINE_LENGTH`.
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<String>> {
+    let mut buf = vec![];
+    let n = reader
+        .take(MAX_LINE_LENGTH as u64)
+        .read_until(b'\n', &mut buf)
+        .await?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if n == MAX_LINE_LENGTH && buf.last() != Some(&b'\n') {
+        return Err(anyhow!("line is longer than {} bytes", MAX_LINE_LENGTH));
+    }
+    let line = String::from_utf8(buf)?;
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+async fn write_line<W: AsyncWrite + Unpin>(writer: &mut W, line: &str) -> Result<()> {
+    writer.write_all(line.as_bytes()).await?;
+    writer.write_all(b"\r\n").await?;
+    Ok(())
+}
+
+/// Writes a text body terminated by a single dot, doubling leading dots.
+async fn write_text<W: AsyncWrite + Unpin>(writer: &mut W, text: &str) -> Result<()> {
+    let mut body = String::with_capacity(text.len() + 8);
+    for line in text.lines() {
+        if line.starts_with('.') {
+            body.push('.');
+        }
+        body.push_str(line);
+        body.push_str("\r\n");
+    }
+    body.push_str(".\r\n");
+    writer.write_all(body.as_bytes()).await?;
+    Ok(())
+}
+
+fn soundex(word: &str) -> String {
+    fn code(c: char) -> Option<char> {
+        match c {
+            'b' | 'f' | 'p' | 'v' => Some('1'),
+            'c' | 'g' | 'j' | 'k' | 'q' | 's' | 'x' | 'z' => Some('2'),
+            'd' | 't' => Some('3'),
+            'l' => Some('4'),
+            'm' | 'n' => Some('5'),
+            'r' => Some('6'),
+            _ => None,
+        }
+    }
+    let mut chars = word
+        .chars()
+        .filter(|x| x.is_ascii_alphabetic())
+        .map(|x| x.to_ascii_lowercase());
+    let first = match chars.next() {
+        Some(v) => v,
+        None => return "".to_string(),
+    };
+    let mut result = first.to_ascii_uppercase().to_string();
+    let mut last = code(first);
+    for c in chars {
+        let current = code(c);
+        if let Some(v) = current {
+            if current != last {
+                result.push(v);
+                if result.len() == 4 {
+                    break;
+                }
+            }
+        }
+        // H and W don't separate letters with the same code.
+        if c != 'h' && c != 'w' {
+            last = current;
+        }
+    }
+    while result.len() < 4 {
+        result.push('0');
+    }
+    result
+}
+
+/// Headwords containing `lower`, ignoring case, at most `MATCH_LIMIT` of them
+fn substring_matches<I: IntoIterator<Item = String>>(words: I, lower: &str) -> Vec<String> {
+    words
+        .into_iter()
+        .filter(|x| x.to_lowercase().contains(lower))
+        .take(MATCH_LIMIT)
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        cur[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_words_and_quotes() {
+        assert_eq!(
+            parse_command("DEFINE * apple\r\n"),
+            vec!["DEFINE", "*", "apple"]
+        );
+        assert_eq!(
+            parse_command("MATCH  wn prefix \"ice cream\""),
+            vec!["MATCH", "wn", "prefix", "ice cream"]
+        );
+        assert_eq!(
+            parse_command("define 'a b' it\\'s"),
+            vec!["define", "a b", "it's"]
+        );
+        assert_eq!(parse_command("show db \"\""), vec!["show", "db", ""]);
+        assert!(parse_command("   ").is_empty());
+    }
+
+    #[test]
+    fn soundex_codes() {
+        assert_eq!(soundex("Robert"), "R163");
+        assert_eq!(soundex("Rupert"), "R163");
+        assert_eq!(soundex("Ashcraft"), "A261");
+        assert_eq!(soundex("Tymczak"), "T522");
+        assert_eq!(soundex("Pfister"), "P236");
+        assert_eq!(soundex("Lee"), "L000");
+        assert_eq!(soundex("123"), "");
+    }
+
+    #[test]
+    fn substring_matches_capped() {
+        let words = ["Ice cream", "cream", "creamy", "scream", "crate"].map(String::from);
+        assert_eq!(
+            substring_matches(words, "cream"),
+            vec!["Ice cream", "cream", "creamy", "scream"]
+        );
+        let words = (0..MATCH_LIMIT * 2).map(|i| format!("word{}", i));
+        let list = substring_matches(words, "word");
+        assert_eq!(list.len(), MATCH_LIMIT);
+        assert_eq!(list[0], "word0");
+        assert!(substring_matches(["apple".to_string()], "pear").is_empty());
+    }
+
+    #[test]
+    fn levenshtein_distances() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("colour", "color"), 1);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+        assert_eq!(levenshtein("café", "cafe"), 1);
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use beluga_core::dictionary::{Dictionary, NodeCache};
+use log::warn;
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::html;
+
+static REDIRECT_PREFIX: &str = "@@@LINK=";
+static MAX_REDIRECT_DEPTH: usize = 8;
+
+/// Returns the target headword if the entry is a MDict style redirect like
+/// `@@@LINK=colour`.
+pub fn redirect_target(content: &str) -> Option<&str> {
+    let rest = content.trim_start().strip_prefix(REDIRECT_PREFIX)?;
+    let target = rest.split(['\r', '\n', '\0']).next().unwrap_or("").trim();
+    if target.is_empty() {
+        None
+    } else {
+        Some(target)
+    }
+}
+
+/// Looks up an entry and follows its redirects. Returns the headword that was
+/// finally found along with its content.
+pub async fn search_entry(
+    dict: &mut Dictionary,
+    cache: Arc<RwLock<NodeCache>>,
+    name: &str,
+) -> Option<(String, String)> {
+    let mut name = name.to_string();
+    let mut visited: Vec<String> = vec![];
+    loop {
+        let content = dict.search_entry(cache.clone(), &name).await?;
+        let target = match redirect_target(&content) {
+            Some(v) => v.to_string(),
+            None => return Some((name, content)),
+        };
+        visited.push(name);
+        if visited.contains(&target) {
+            warn!("redirect loop: {} -> {}", visited.join(" -> "), target);
+            return None;
+        }
+        if visited.len() >= MAX_REDIRECT_DEPTH {
+            warn!("too many redirects: {} -> {}", visited.join(" -> "), target);
+            return None;
+        }
+        name = target;
+    }
+}
+
+static MAX_ENTRIES: usize = 16;
+
+#[derive(Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub content: String,
+}
+
+/// Looks up every entry of a headword. Homographs are stored under keys that
+/// differ only in case or a trailing number, like `bank`, `Bank` and `bank2`.
+/// The exact match comes first and entries with the same content (e.g. several
+/// redirects to one entry) are returned once.
+pub async fn search_entries(
+    dict: &mut Dictionary,
+    cache: Arc<RwLock<NodeCache>>,
+    name: &str,
+) -> Vec<Entry> {
+    let mut candidates = vec![name.to_string()];
+    let key = homograph_key(name);
+    for wd in dict
+        .search(cache.clone(), name, false, MAX_ENTRIES, 0)
+        .await
+    {
+        if !candidates.contains(&wd) && homograph_key(&wd) == key {
+            candidates.push(wd);
+        }
+    }
+    let mut list: Vec<Entry> = vec![];
+    for wd in candidates {
+        if let Some((headword, content)) = search_entry(dict, cache.clone(), &wd).await {
+            if !list.iter().any(|x| x.content == content) {
+                list.push(Entry {
+                    name: headword,
+                    content,
+                });
+            }
+        }
+    }
+    list
+}
+
+fn homograph_key(name: &str) -> String {
+    name.trim()
+        .trim_end_matches(|c: char| {
+            c.is_ascii_digit() || c.is_whitespace() || "¹²³⁴⁵⁶⁷⁸⁹⁰".contains(c)
+        })
+        .to_lowercase()
+}
+
+static SOUND_PREFIX: &str = "sound://";
+
+/// Returns the names of audio resources referenced by an entry, through
+/// `sound://` links or audio elements, in the order they appear.
+pub fn sound_resources(content: &str) -> Vec<String> {
+    let mut list: Vec<String> = vec![];
+    for token in html::tokenize(content) {
+        let (name, attrs) = match &token {
+            html::Token::StartTag { name, attrs, .. } => (name.as_str(), attrs),
+            _ => continue,
+        };
+        let value = match name {
+            "a" => html::get_attr(attrs, "href").filter(|x| x.starts_with(SOUND_PREFIX)),
+            "audio" | "source" => html::get_attr(attrs, "src"),
+            _ => None,
+        };
+        let value = match value {
+            Some(v) => v.trim().to_string(),
+            None => continue,
+        };
+        let res = match value.strip_prefix(SOUND_PREFIX) {
+            Some(v) => v.to_string(),
+            // Only sounds of the dictionary
+            None if !value.contains(':') && !value.starts_with("//") => {
+                value.trim_start_matches('/').to_string()
+            }
+            None => continue,
+        };
+        if !res.is_empty() && !list.contains(&res) {
+            list.push(res);
+        }
+    }
+    list
+}
@@ -0,0 +1,281 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
+use beluga_core::dictionary::{Dictionary, NodeCache};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::{
+    entry::{self, Entry},
+    html,
+    server::ENTRY_SEPARATOR,
+    utils::glob_match,
+};
+
+/// Results of one prefix search. A prefix with this many words is split
+/// into longer prefixes as the search may have left some out.
+static ENUM_LIMIT: usize = 1000;
+static REFERENCE_ATTRS: [&str; 4] = ["src", "href", "poster", "data"];
+
+/// A headword with its entries as `/@entries` returns them
+#[derive(Serialize)]
+struct EntryLine<'a> {
+    word: &'a str,
+    entries: &'a [Entry],
+}
+
+/// Headwords found by `list_headwords`
+pub struct Headwords {
+    pub words: BTreeSet<String>,
+    /// False if a search was cut off. The words it left out are searched with
+    /// longer prefixes made of the characters seen in headwords, so words
+    /// with other characters may be missing.
+    pub complete: bool,
+}
+
+/// Lists all headwords of a dictionary. There's no way to iterate the keys of
+/// a dictionary, so this relies on prefix search, splitting a prefix by the
+/// characters seen in headwords until its words fit in one search. Prefixes
+/// already split are split again by characters seen later.
+pub async fn list_headwords(dict: &mut Dictionary, cache: Arc<RwLock<NodeCache>>) -> Headwords {
+    let mut words: BTreeSet<String> = BTreeSet::new();
+    let mut alphabet: BTreeSet<char> = ('a'..='z').chain('0'..='9').collect();
+    let mut prefixes = vec![String::new()];
+    let mut split: Vec<String> = vec![];
+    let mut searched: BTreeSet<String> = BTreeSet::new();
+    let mut complete = true;
+    while let Some(prefix) = prefixes.pop() {
+        if !searched.insert(prefix.to_lowercase()) {
+            continue;
+        }
+        let list = dict
+            .search(cache.clone(), &prefix, false, ENUM_LIMIT, 0)
+            .await;
+        // An empty prefix may not match anything, which tells nothing.
+        let saturated = list.len() >= ENUM_LIMIT || (prefix.is_empty() && list.is_empty());
+        complete &= !saturated;
+        let mut new_chars: Vec<char> = vec![];
+        for wd in list {
+            for c in wd.to_lowercase().chars() {
+                if alphabet.insert(c) {
+                    new_chars.push(c);
+                }
+            }
+            words.insert(wd);
+        }
+        for p in &split {
+            for c in &new_chars {
+                prefixes.push(format!("{}{}", p, c));
+            }
+        }
+        if saturated || prefix.is_empty() {
+            for c in &alphabet {
+                prefixes.push(format!("{}{}", prefix, c));
+            }
+            split.push(prefix);
+        }
+    }
+    Headwords { words, complete }
+}
+
+/// Writes the entries of headwords, with redirects followed and homographs
+/// included, as JSON lines if `output` ends with `.jsonl`, otherwise as one
+/// HTML file per headword in the `output` folder. Headwords mapping to the
+/// same file name, ignoring case, get a numeric suffix. Returns the number
+/// of headwords written.
+pub async fn export_entries(
+    dict: &mut Dictionary,
+    cache: Arc<RwLock<NodeCache>>,
+    words: &BTreeSet<String>,
+    output: &Path,
+) -> Result<usize> {
+    let jsonl = output
+        .extension()
+        .is_some_and(|x| x.eq_ignore_ascii_case("jsonl"));
+    let mut writer = if jsonl {
+        if let Some(dir) = output.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Some(BufWriter::new(File::create(output)?))
+    } else {
+        fs::create_dir_all(output)?;
+        None
+    };
+    let mut names: HashSet<String> = HashSet::new();
+    let mut count = 0;
+    for wd in words {
+        let entries = entry::search_entries(dict, cache.clone(), wd).await;
+        if entries.is_empty() {
+            continue;
+        }
+        match writer.as_mut() {
+            Some(w) => {
+                serde_json::to_writer(
+                    &mut *w,
+                    &EntryLine {
+                        word: wd,
+                        entries: &entries,
+                    },
+                )?;
+                w.write_all(b"\n")?;
+            }
+            None => {
+                let name = unique_name(&mut names, &file_name(wd));
+                let file = output.join(format!("{}.html", name));
+                let content = entries
+                    .into_iter()
+                    .map(|x| x.content)
+                    .collect::<Vec<String>>()
+                    .join(ENTRY_SEPARATOR);
+                fs::write(file, content)?;
+            }
+        }
+        count += 1;
+    }
+    if let Some(mut w) = writer {
+        w.flush()?;
+    }
+    Ok(count)
+}
+
+/// Writes a resource under `dir`, keeping its folders. Returns the file.
+pub async fn export_resource(
+    dict: &mut Dictionary,
+    cache: Arc<RwLock<NodeCache>>,
+    name: &str,
+    dir: &Path,
+) -> Result<PathBuf> {
+    let data = dict
+        .search_resource(cache, name)
+        .await
+        .ok_or_else(|| anyhow!("no resource \"{}\"", name))?;
+    let file = resource_path(dir, name).ok_or_else(|| anyhow!("invalid name \"{}\"", name))?;
+    if let Some(v) = file.parent() {
+        fs::create_dir_all(v)?;
+    }
+    fs::write(&file, data)?;
+    Ok(file)
+}
+
+/// Lists the resources matching a glob. Resources can't be iterated either,
+/// so the candidates are the ones referenced by the dictionary stylesheet and
+/// by entries of `words`.
+pub async fn find_resources(
+    dict: &mut Dictionary,
+    cache: Arc<RwLock<NodeCache>>,
+    words: &BTreeSet<String>,
+    pattern: &str,
+) -> BTreeSet<String> {
+    let mut names: BTreeSet<String> = BTreeSet::new();
+    if let Ok((css, _)) = dict.get_css_js(false).await {
+        css_references(&css, &mut names);
+    }
+    for wd in words {
+        for v in entry::search_entries(dict, cache.clone(), wd).await {
+            html_references(&v.content, &mut names);
+        }
+    }
+    names.retain(|x| glob_match(pattern, x));
+    names
+}
+
+fn html_references(content: &str, names: &mut BTreeSet<String>) {
+    let mut in_style = false;
+    for token in html::tokenize(content) {
+        match token {
+            html::Token::StartTag { name, attrs, .. } => {
+                in_style = name == "style";
+                for (key, value) in &attrs {
+                    let value = match value {
+                        Some(v) => v,
+                        None => continue,
+                    };
+                    if REFERENCE_ATTRS.contains(&key.as_str()) {
+                        add_reference(value, names);
+                    } else if key == "style" {
+                        css_references(value, names);
+                    }
+                }
+            }
+            html::Token::EndTag { .. } => in_style = false,
+            html::Token::Text(s) if in_style => css_references(s, names),
+            _ => {}
+        }
+    }
+}
+
+fn css_references(css: &str, names: &mut BTreeSet<String>) {
+    let mut rest = css;
+    while let Some(i) = rest.find("url(") {
+        rest = &rest[i + 4..];
+        let end = rest.find(')').unwrap_or(rest.len());
+        add_reference(rest[..end].trim().trim_matches(['"', '\'']), names);
+        rest = &rest[end..];
+    }
+}
+
+fn add_reference(url: &str, names: &mut BTreeSet<String>) {
+    let url = url.trim();
+    let name = if let Some(v) = url.strip_prefix("sound://") {
+        v
+    } else if url.contains(':') || url.starts_with("//") || url.starts_with('#') {
+        return;
+    } else {
+        url.split(['#', '?']).next().unwrap_or("")
+    };
+    let name = name.trim_start_matches(['/', '\\']);
+    if !name.is_empty() {
+        names.insert(name.to_string());
+    }
+}
+
+/// Maps a resource name like `\img\a.png` to a file under `dir`, ignoring
+/// components that would leave it.
+fn resource_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    let mut file = dir.to_path_buf();
+    let mut empty = true;
+    for part in name.split(['/', '\\']) {
+        if matches!(
+            Path::new(part).components().next(),
+            Some(Component::Normal(_))
+        ) && Path::new(part).components().count() == 1
+        {
+            file.push(part);
+            empty = false;
+        }
+    }
+    if empty {
+        None
+    } else {
+        Some(file)
+    }
+}
+
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_control() || "/\\:*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Appends `-2`, `-3` and so on to a name already used.
+fn unique_name(used: &mut HashSet<String>, name: &str) -> String {
+    let mut result = name.to_string();
+    let mut i = 1;
+    while !used.insert(result.to_lowercase()) {
+        i += 1;
+        result = format!("{}-{}", name, i);
+    }
+    result
+}
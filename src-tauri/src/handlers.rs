@@ -1,14 +1,23 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use crate::{
     base::Pagination,
     database::Database,
+    entry::{self, Entry},
     error::Result,
+    html,
     model::{book::BookModel, word::WordModel, RowID},
-    settings::{Configuration, DictItem},
-    utils::current_timestamp,
+    ocr::{OcrRegion, OcrResult, OcrStatus},
+    server::{server_status, ServerStatus},
+    settings::{
+        Configuration, DictGroup, DictItem, CLIPBOARD_SCRIPTS, OCR_MODE_CURSOR, OCR_MODE_REGION,
+    },
+    shortcuts::{self, ShortcutStatus},
+    theme,
+    utils::{current_timestamp, encode_uri_component},
 };
-use serde::Deserialize;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
 use tauri::{command, AppHandle, Manager, State};
 use tokio::fs;
 
@@ -28,11 +37,17 @@ pub fn open_devtools(ah: AppHandle) -> Result<()> {
 }
 
 #[command]
-pub async fn get_server_port(state: State<'_, AppState>) -> Result<u32> {
+pub async fn get_server_port(state: State<'_, AppState>) -> Result<u16> {
     let settings_lock = state.settings.read().await;
     Ok(settings_lock.server_port)
 }
 
+/// The port of the HTTP server, or why it fails to start
+#[command]
+pub fn get_server_status() -> ServerStatus {
+    server_status()
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SearchParams {
     pub id: u32,
@@ -63,6 +78,111 @@ pub async fn search(state: State<'_, AppState>, req: SearchParams) -> Result<Vec
     Ok(r)
 }
 
+#[derive(Debug, Deserialize)]
+pub struct EntryParams {
+    pub id: u32,
+    pub name: String,
+}
+
+#[command]
+pub async fn get_entry_text(
+    state: State<'_, AppState>,
+    req: EntryParams,
+) -> Result<Option<String>> {
+    let dict = if let Some(v) = state.get_dictionary(req.id).await {
+        v
+    } else {
+        return Ok(None);
+    };
+    let mut d = dict.lock().await;
+    let entries = entry::search_entries(&mut d, state.cache.clone(), &req.name).await;
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    let r = entries
+        .iter()
+        .map(|x| html::to_text(&x.content))
+        .collect::<Vec<String>>()
+        .join("\n\n");
+    Ok(Some(r))
+}
+
+#[command]
+pub async fn search_entries(state: State<'_, AppState>, req: EntryParams) -> Result<Vec<Entry>> {
+    let dict = if let Some(v) = state.get_dictionary(req.id).await {
+        v
+    } else {
+        return Ok(vec![]);
+    };
+    let mut d = dict.lock().await;
+    let r = entry::search_entries(&mut d, state.cache.clone(), &req.name).await;
+    Ok(r)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PronunciationParams {
+    pub name: String,
+    /// Defaults to all enabled dictionaries
+    pub id: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct Pronunciation {
+    pub dict_id: u32,
+    pub dict: String,
+    /// Resource name in the dictionary
+    pub name: String,
+    pub url: String,
+}
+
+#[command]
+pub async fn get_pronunciations(
+    state: State<'_, AppState>,
+    req: PronunciationParams,
+) -> Result<Vec<Pronunciation>> {
+    let settings_lock = state.settings.read().await;
+    let port = settings_lock.server_port;
+    let dicts: Vec<DictItem> = settings_lock
+        .config
+        .dicts
+        .iter()
+        .filter(|x| match req.id {
+            Some(id) => x.id == id,
+            None => x.available,
+        })
+        .cloned()
+        .collect();
+    drop(settings_lock);
+    let mut list: Vec<Pronunciation> = vec![];
+    for item in dicts {
+        let dict = if let Some(v) = state.get_dictionary(item.id).await {
+            v
+        } else {
+            continue;
+        };
+        let mut d = dict.lock().await;
+        for v in entry::search_entries(&mut d, state.cache.clone(), &req.name).await {
+            for name in entry::sound_resources(&v.content) {
+                if list.iter().any(|x| x.dict_id == item.id && x.name == name) {
+                    continue;
+                }
+                list.push(Pronunciation {
+                    dict_id: item.id,
+                    dict: item.name.clone(),
+                    url: format!(
+                        "http://127.0.0.1:{}/@resource?dict_id={}&name={}",
+                        port,
+                        item.id,
+                        encode_uri_component(&name)
+                    ),
+                    name,
+                });
+            }
+        }
+    }
+    Ok(list)
+}
+
 #[command]
 pub async fn resize_cache(state: State<'_, AppState>, req: u64) -> Result<()> {
     let mut cache_lock = state.cache.write().await;
@@ -80,13 +200,36 @@ pub async fn get_settings(state: State<'_, AppState>) -> Result<Configuration> {
 pub struct SettingsParams {
     pub dict_dir: Option<String>,
     pub dicts: Option<Vec<DictItem>>,
+    pub dict_groups: Option<Vec<DictGroup>>,
+    /// Switches to the dictionary group of this name
+    pub dict_group: Option<String>,
     pub cache_size: Option<u32>,
     pub prefix_limit: Option<u32>,
     pub phrase_limit: Option<u32>,
     pub ocr_width: Option<u32>,
     pub ocr_height: Option<u32>,
-    pub ocr_shortcut: Option<String>,
+    pub shortcuts: Option<BTreeMap<String, String>>,
+    pub ocr_shortcut_enabled: Option<bool>,
+    pub ocr_mode: Option<String>,
+    pub ocr_detection_model: Option<String>,
+    pub ocr_recognition_model: Option<String>,
+    pub ocr_grayscale: Option<bool>,
+    pub ocr_auto_invert: Option<bool>,
+    pub ocr_upscale: Option<bool>,
+    pub ocr_normalize_contrast: Option<bool>,
+    pub ocr_dpi_aware_crop: Option<bool>,
+    pub ocr_debug: Option<bool>,
+    pub clipboard_watch: Option<bool>,
+    pub clipboard_max_length: Option<u32>,
+    pub clipboard_scripts: Option<Vec<String>>,
+    pub popup_lookup: Option<bool>,
     pub dev_mode: Option<bool>,
+    pub dark_mode: Option<bool>,
+    pub font_scale: Option<u32>,
+    pub sanitize_entries: Option<bool>,
+    pub http_port: Option<u16>,
+    pub dict_server: Option<bool>,
+    pub dict_server_port: Option<u16>,
 }
 
 #[command]
@@ -96,6 +239,39 @@ pub async fn set_settings(
     req: SettingsParams,
 ) -> Result<()> {
     let mut settings = state.settings.write().await;
+    // Checks every field first so a bad one leaves the settings unchanged.
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    if let Some(v) = &req.shortcuts {
+        crate::shortcuts::validate_shortcuts(v)?;
+    }
+    if let Some(v) = &req.ocr_mode {
+        if v != OCR_MODE_CURSOR && v != OCR_MODE_REGION {
+            return Err(anyhow!("invalid OCR mode: {}", v).into());
+        }
+    }
+    if let Some(v) = &req.clipboard_scripts {
+        if let Some(x) = v.iter().find(|x| !CLIPBOARD_SCRIPTS.contains(&x.as_str())) {
+            return Err(anyhow!("invalid script: {}", x).into());
+        }
+    }
+    if let Some(v) = &req.dict_groups {
+        if v.iter().any(|x| x.name.trim().is_empty()) {
+            return Err(anyhow!("empty dictionary group name").into());
+        }
+    }
+    if let Some(v) = &req.dict_group {
+        let groups = req
+            .dict_groups
+            .as_ref()
+            .unwrap_or(&settings.config.dict_groups);
+        if !groups.iter().any(|x| &x.name == v) {
+            return Err(anyhow!("no dictionary group {}", v).into());
+        }
+    }
+    if req.dict_server_port == Some(0) {
+        return Err(anyhow!("invalid DICT server port: 0").into());
+    }
+
     let mut need_reload = false;
     if let Some(v) = req.dict_dir {
         settings.config.dict_dir = v;
@@ -104,6 +280,12 @@ pub async fn set_settings(
     if let Some(v) = req.dicts {
         settings.config.dicts = v;
     }
+    if let Some(v) = req.dict_groups {
+        settings.config.dict_groups = v;
+    }
+    if let Some(v) = req.dict_group {
+        settings.config.switch_dict_group(&v)?;
+    }
     if let Some(v) = req.cache_size {
         settings.config.cache_size = v;
     }
@@ -119,12 +301,78 @@ pub async fn set_settings(
     if let Some(v) = req.ocr_height {
         settings.config.ocr_height = v;
     }
-    if let Some(v) = req.ocr_shortcut {
-        settings.config.ocr_shortcut = v;
+    let mut need_register_shortcuts = false;
+    if let Some(v) = req.shortcuts {
+        need_register_shortcuts = v != settings.config.shortcuts;
+        settings.config.shortcuts = v;
+    }
+    if let Some(v) = req.ocr_shortcut_enabled {
+        need_register_shortcuts |= v != settings.config.ocr_shortcut_enabled;
+        settings.config.ocr_shortcut_enabled = v;
+    }
+    if let Some(v) = req.ocr_mode {
+        settings.config.ocr_mode = v;
+    }
+    let mut need_reload_ocr = false;
+    if let Some(v) = req.ocr_detection_model {
+        need_reload_ocr |= v != settings.config.ocr_detection_model;
+        settings.config.ocr_detection_model = v;
+    }
+    if let Some(v) = req.ocr_recognition_model {
+        need_reload_ocr |= v != settings.config.ocr_recognition_model;
+        settings.config.ocr_recognition_model = v;
+    }
+    if let Some(v) = req.ocr_grayscale {
+        settings.config.ocr_grayscale = v;
+    }
+    if let Some(v) = req.ocr_auto_invert {
+        settings.config.ocr_auto_invert = v;
+    }
+    if let Some(v) = req.ocr_upscale {
+        settings.config.ocr_upscale = v;
+    }
+    if let Some(v) = req.ocr_normalize_contrast {
+        settings.config.ocr_normalize_contrast = v;
+    }
+    if let Some(v) = req.ocr_dpi_aware_crop {
+        settings.config.ocr_dpi_aware_crop = v;
+    }
+    if let Some(v) = req.ocr_debug {
+        settings.config.ocr_debug = v;
+    }
+    if let Some(v) = req.clipboard_watch {
+        settings.config.clipboard_watch = v;
+    }
+    if let Some(v) = req.clipboard_max_length {
+        settings.config.clipboard_max_length = v;
+    }
+    if let Some(v) = req.clipboard_scripts {
+        settings.config.clipboard_scripts = v;
+    }
+    if let Some(v) = req.popup_lookup {
+        settings.config.popup_lookup = v;
     }
     if let Some(v) = req.dev_mode {
         settings.config.dev_mode = v;
     }
+    if let Some(v) = req.dark_mode {
+        settings.config.dark_mode = v;
+    }
+    if let Some(v) = req.font_scale {
+        settings.config.font_scale = v;
+    }
+    if let Some(v) = req.sanitize_entries {
+        settings.config.sanitize_entries = v;
+    }
+    if let Some(v) = req.http_port {
+        settings.config.http_port = v;
+    }
+    if let Some(v) = req.dict_server {
+        settings.config.dict_server = v;
+    }
+    if let Some(v) = req.dict_server_port {
+        settings.config.dict_server_port = v;
+    }
     settings.save()?;
     drop(settings);
 
@@ -133,10 +381,39 @@ pub async fn set_settings(
     }
 
     let settings = state.settings.read().await;
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    if need_reload_ocr {
+        // The status event tells when the new engine is in use.
+        let ah = ah.clone();
+        let detection_model = settings.config.ocr_detection_model.clone();
+        let recognition_model = settings.config.ocr_recognition_model.clone();
+        tokio::task::spawn_blocking(move || {
+            crate::ocr::engine::load_engine(&ah, &detection_model, &recognition_model)
+        });
+    }
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    if need_register_shortcuts {
+        crate::shortcuts::register_shortcuts(&ah, &settings.config.active_shortcuts());
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    let _ = (need_reload_ocr, need_register_shortcuts);
     settings.notify_changed(ah);
     Ok(())
 }
 
+/// Shortcuts registered, with the ones failing to register
+#[command]
+pub fn get_shortcut_status() -> Vec<ShortcutStatus> {
+    shortcuts::shortcut_status()
+}
+
+#[command]
+pub async fn get_theme_dir(state: State<'_, AppState>) -> Result<String> {
+    let settings_lock = state.settings.read().await;
+    let dir = theme::init_theme_directory(&settings_lock.config_dir())?;
+    Ok(dir.to_string_lossy().to_string())
+}
+
 #[command]
 pub async fn reload_dicts(state: State<'_, AppState>) -> Result<()> {
     state.load_dictionaries().await?;
@@ -203,6 +480,7 @@ pub async fn import_book(db: State<'_, Arc<Database>>, req: String) -> Result<()
                 familiar: 0,
                 book_id,
                 create_time: now,
+                context: "".to_string(),
             })
             .collect::<Vec<WordModel>>();
         WordModel::bulk_insert(&mut *tx, &words).await?;
@@ -277,19 +555,27 @@ pub async fn get_word_list(
     Ok(pg)
 }
 
+#[derive(Debug, Deserialize)]
+pub struct AddWordParams {
+    pub book_id: RowID,
+    pub name: String,
+    /// The sentence the word was looked up in
+    pub context: Option<String>,
+}
+
 #[command]
-pub async fn add_word(db: State<'_, Arc<Database>>, req: (RowID, String)) -> Result<()> {
-    let (book_id, name) = req;
+pub async fn add_word(db: State<'_, Arc<Database>>, req: AddWordParams) -> Result<()> {
     let mut conn = db.pool.acquire().await?;
-    if WordModel::exist_by_name(&mut conn, book_id, &name).await? {
+    if WordModel::exist_by_name(&mut conn, req.book_id, &req.name).await? {
         return Ok(());
     }
     let mut word = WordModel {
         id: 0,
-        name,
+        name: req.name,
         familiar: 0,
-        book_id,
+        book_id: req.book_id,
         create_time: current_timestamp(),
+        context: req.context.unwrap_or_default(),
     };
     word.insert(&mut conn).await?;
     Ok(())
@@ -309,6 +595,7 @@ pub async fn set_word_familiar(db: State<'_, Arc<Database>>, req: FamiliarParams
         familiar: req.familiar,
         book_id: 0,
         create_time: 0,
+        context: "".to_string(),
     };
     let mut conn = db.pool.acquire().await?;
     word.update(&mut conn, vec!["familiar"]).await?;
@@ -321,3 +608,83 @@ pub async fn delete_words(db: State<'_, Arc<Database>>, req: Vec<RowID>) -> Resu
     WordModel::delete(&mut conn, &req[..]).await?;
     Ok(())
 }
+
+/// Recognizes the text in the region selected in the OCR overlay and sends it
+/// to the main window.
+#[command]
+pub async fn ocr_region(ah: AppHandle, req: OcrRegion) -> Result<()> {
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    {
+        use crate::ocr::region;
+        let r = region::recognize_region(&ah, req).await;
+        region::close_overlay(&ah);
+        region::deliver_result(&ah, &r?)?;
+        Ok(())
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        let _ = (ah, req);
+        Err(anyhow!("OCR is not supported").into())
+    }
+}
+
+#[command]
+pub fn get_ocr_status() -> OcrStatus {
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    {
+        crate::ocr::engine::ocr_status()
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        OcrStatus {
+            loaded: false,
+            error: Some("OCR is not supported".to_string()),
+        }
+    }
+}
+
+#[command]
+pub fn close_ocr_overlay(ah: AppHandle) {
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    crate::ocr::region::close_overlay(&ah);
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    let _ = ah;
+}
+
+#[command]
+pub fn close_popup(ah: AppHandle) {
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    crate::popup::hide_popup(&ah);
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    let _ = ah;
+}
+
+/// Recognizes all text of an image file, e.g. a screenshot or scanned page.
+#[command]
+pub async fn ocr_image_file(ah: AppHandle, req: String) -> Result<OcrResult> {
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    {
+        let r = crate::ocr::recognize::recognize_file(&ah, std::path::Path::new(&req)).await?;
+        Ok(r)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        let _ = (ah, req);
+        Err(anyhow!("OCR is not supported").into())
+    }
+}
+
+/// Recognizes all text of the image on the clipboard.
+#[command]
+pub async fn ocr_clipboard_image(ah: AppHandle) -> Result<OcrResult> {
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    {
+        let r = crate::ocr::recognize::recognize_clipboard(&ah).await?;
+        Ok(r)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        let _ = ah;
+        Err(anyhow!("OCR is not supported").into())
+    }
+}
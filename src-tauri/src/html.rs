@@ -0,0 +1,741 @@
+pub type Attrs = Vec<(String, Option<String>)>;
+
+pub enum Token<'a> {
+    Text(&'a str),
+    StartTag {
+        name: String,
+        attrs: Attrs,
+        self_closing: bool,
+        raw: &'a str,
+    },
+    EndTag {
+        name: String,
+        raw: &'a str,
+    },
+    Comment(&'a str),
+    Doctype(&'a str),
+}
+
+static RAW_TEXT_TAGS: [&str; 4] = ["script", "style", "textarea", "title"];
+
+static VOID_TAGS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+static BLOCK_TAGS: [&str; 28] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "section",
+    "tr",
+    "ul",
+];
+
+pub fn is_void_tag(name: &str) -> bool {
+    VOID_TAGS.contains(&name)
+}
+
+pub fn is_block_tag(name: &str) -> bool {
+    BLOCK_TAGS.contains(&name)
+}
+
+pub fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let bytes = html.as_bytes();
+    let mut tokens = vec![];
+    let mut pos = 0;
+    let mut text_start = 0;
+    while pos < bytes.len() {
+        if bytes[pos] != b'<' {
+            pos += 1;
+            continue;
+        }
+        let rest = &html[pos..];
+        let (token, len) = if rest.starts_with("<!--") {
+            let end = rest
+                .strip_prefix("<!--")
+                .and_then(|x| x.find("-->"))
+                .map(|i| i + 7)
+                .unwrap_or(rest.len());
+            (Token::Comment(&rest[..end]), end)
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            let end = rest.find('>').map(|i| i + 1).unwrap_or(rest.len());
+            (Token::Doctype(&rest[..end]), end)
+        } else if rest.starts_with("</") {
+            match parse_tag(rest, 2) {
+                Some((name, _, _, len)) => (
+                    Token::EndTag {
+                        name,
+                        raw: &rest[..len],
+                    },
+                    len,
+                ),
+                None => {
+                    pos += 1;
+                    continue;
+                }
+            }
+        } else {
+            match parse_tag(rest, 1) {
+                Some((name, attrs, self_closing, len)) => (
+                    Token::StartTag {
+                        name,
+                        attrs,
+                        self_closing,
+                        raw: &rest[..len],
+                    },
+                    len,
+                ),
+                None => {
+                    pos += 1;
+                    continue;
+                }
+            }
+        };
+        if text_start < pos {
+            tokens.push(Token::Text(&html[text_start..pos]));
+        }
+        pos += len;
+        text_start = pos;
+        if let Token::StartTag {
+            name, self_closing, ..
+        } = &token
+        {
+            if !self_closing && RAW_TEXT_TAGS.contains(&name.as_str()) {
+                let close = format!("</{}", name);
+                let lower = html[pos..].to_ascii_lowercase();
+                let end = lower.find(&close).map(|i| pos + i).unwrap_or(html.len());
+                tokens.push(token);
+                if pos < end {
+                    tokens.push(Token::Text(&html[pos..end]));
+                }
+                pos = end;
+                text_start = pos;
+                continue;
+            }
+        }
+        tokens.push(token);
+    }
+    if text_start < bytes.len() {
+        tokens.push(Token::Text(&html[text_start..]));
+    }
+    tokens
+}
+
+/// Parses a tag starting at `s[start..]` (just after `<` or `</`) and returns
+/// its lowercased name, attributes, whether it is self-closing and its total
+/// length including the closing `>`.
+fn parse_tag(s: &str, start: usize) -> Option<(String, Attrs, bool, usize)> {
+    let bytes = s.as_bytes();
+    let mut i = start;
+    while i < bytes.len()
+        && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'-' || bytes[i] == b':')
+    {
+        i += 1;
+    }
+    if i == start || !bytes[start].is_ascii_alphabetic() {
+        return None;
+    }
+    let name = s[start..i].to_ascii_lowercase();
+    let mut attrs = vec![];
+    let mut self_closing = false;
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i >= bytes.len() {
+            return Some((name, attrs, self_closing, bytes.len()));
+        }
+        match bytes[i] {
+            b'>' => return Some((name, attrs, self_closing, i + 1)),
+            b'/' => {
+                self_closing = true;
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        self_closing = false;
+        let key_start = i;
+        while i < bytes.len()
+            && !bytes[i].is_ascii_whitespace()
+            && bytes[i] != b'='
+            && bytes[i] != b'>'
+            && !(bytes[i] == b'/' && bytes.get(i + 1) == Some(&b'>'))
+        {
+            i += 1;
+        }
+        let key = s[key_start..i].to_ascii_lowercase();
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i < bytes.len() && bytes[i] == b'=' {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            let value = if i < bytes.len() && (bytes[i] == b'"' || bytes[i] == b'\'') {
+                let quote = bytes[i];
+                let value_start = i + 1;
+                let value_end = s[value_start..]
+                    .bytes()
+                    .position(|b| b == quote)
+                    .map(|x| value_start + x)
+                    .unwrap_or(bytes.len());
+                i = (value_end + 1).min(bytes.len());
+                &s[value_start..value_end]
+            } else {
+                let value_start = i;
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                    i += 1;
+                }
+                &s[value_start..i]
+            };
+            attrs.push((key, Some(decode_entities(value))));
+        } else if !key.is_empty() {
+            attrs.push((key, None));
+        } else {
+            i += 1;
+        }
+    }
+}
+
+pub fn get_attr<'a>(attrs: &'a [(String, Option<String>)], key: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(k, _)| k == key)
+        .and_then(|(_, v)| v.as_deref())
+}
+
+pub fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let end = match rest[1..].find(|c: char| c == ';' || c == '&' || c.is_whitespace()) {
+            Some(j) if rest.as_bytes()[j + 1] == b';' && j <= 10 => j + 1,
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let name = &rest[1..end];
+        let decoded = if let Some(num) = name.strip_prefix('#') {
+            let code = if let Some(hex) = num.strip_prefix('x').or_else(|| num.strip_prefix('X')) {
+                u32::from_str_radix(hex, 16).ok()
+            } else {
+                num.parse::<u32>().ok()
+            };
+            code.and_then(char::from_u32)
+        } else {
+            match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                "ndash" => Some('–'),
+                "mdash" => Some('—'),
+                "hellip" => Some('…'),
+                "lsquo" => Some('‘'),
+                "rsquo" => Some('’'),
+                "ldquo" => Some('“'),
+                "rdquo" => Some('”'),
+                "middot" => Some('·'),
+                "bull" => Some('•'),
+                "copy" => Some('©'),
+                "reg" => Some('®'),
+                "deg" => Some('°'),
+                "times" => Some('×'),
+                _ => None,
+            }
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+static SKIPPED_TAGS: [&str; 6] = ["head", "noscript", "script", "style", "template", "title"];
+
+static EXAMPLE_CLASSES: [&str; 6] = ["eg", "ex", "exa", "examp", "example", "x"];
+
+static INDENT_WIDTH: usize = 4;
+
+/// Converts dictionary HTML to plain text. Scripts and styles are dropped,
+/// block elements and `<br>` become line breaks, list items keep their
+/// numbers or bullets, and examples and quotes are indented.
+pub fn to_text(html: &str) -> String {
+    let mut renderer = TextRenderer::default();
+    for token in tokenize(html) {
+        match token {
+            Token::StartTag {
+                name,
+                attrs,
+                self_closing,
+                ..
+            } => renderer.start_tag(name, &attrs, self_closing),
+            Token::EndTag { name, .. } => renderer.end_tag(&name),
+            Token::Text(s) => renderer.text(s),
+            Token::Comment(_) | Token::Doctype(_) => {}
+        }
+    }
+    renderer.out.trim_end().to_string()
+}
+
+struct Frame {
+    name: String,
+    indent: usize,
+    skip: bool,
+    block: bool,
+    example: bool,
+    list: Option<ListState>,
+}
+
+struct ListState {
+    ordered: bool,
+    counter: i64,
+}
+
+#[derive(Default)]
+struct TextRenderer {
+    out: String,
+    stack: Vec<Frame>,
+    indent: usize,
+    skip: usize,
+    pending_space: bool,
+    pending_marker: Option<String>,
+}
+
+impl TextRenderer {
+    fn start_tag(&mut self, name: String, attrs: &[(String, Option<String>)], self_closing: bool) {
+        if self.skip > 0 {
+            if !self_closing && !is_void_tag(&name) {
+                self.push_frame(name, true, false, None);
+            }
+            return;
+        }
+        match name.as_str() {
+            "br" => {
+                self.line_break();
+                return;
+            }
+            "hr" => {
+                self.line_break();
+                self.write("―――");
+                self.line_break();
+                return;
+            }
+            "td" | "th" => {
+                if !self.at_line_start() {
+                    self.out.push('\t');
+                }
+                self.pending_space = false;
+            }
+            _ => {}
+        }
+        if self_closing || is_void_tag(&name) {
+            return;
+        }
+        if SKIPPED_TAGS.contains(&name.as_str()) {
+            self.push_frame(name, true, false, None);
+            return;
+        }
+        let indent = self.indent;
+        let mut list = None;
+        let mut block = is_block_tag(&name) || name == "table";
+        let mut example = false;
+        match name.as_str() {
+            "ol" | "ul" => {
+                let start = get_attr(attrs, "start")
+                    .and_then(|x| x.trim().parse::<i64>().ok())
+                    .unwrap_or(1);
+                list = Some(ListState {
+                    ordered: name == "ol",
+                    counter: start,
+                });
+                // Nested lists are indented below their item.
+                if self.stack.iter().any(|x| x.name == "li") {
+                    self.indent = indent + 2;
+                }
+            }
+            "li" => {
+                self.line_break();
+                let marker = match self.stack.iter_mut().rev().find_map(|x| x.list.as_mut()) {
+                    Some(v) if v.ordered => {
+                        if let Some(n) =
+                            get_attr(attrs, "value").and_then(|x| x.trim().parse().ok())
+                        {
+                            v.counter = n;
+                        }
+                        let marker = format!("{}. ", v.counter);
+                        v.counter += 1;
+                        marker
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.indent = indent + marker.chars().count();
+                self.pending_marker = Some(marker);
+            }
+            "blockquote" | "dd" => {
+                self.indent = indent + INDENT_WIDTH;
+            }
+            _ if is_example(attrs) => {
+                // Examples get a line of their own even if they are inline
+                // elements, but nested examples are not indented again.
+                example = true;
+                block = true;
+                if !self.stack.iter().any(|x| x.example) {
+                    self.indent = indent + INDENT_WIDTH;
+                }
+            }
+            _ => {}
+        }
+        if block {
+            self.line_break();
+        }
+        self.stack.push(Frame {
+            name,
+            indent,
+            skip: false,
+            block,
+            example,
+            list,
+        });
+    }
+
+    fn end_tag(&mut self, name: &str) {
+        let i = match self.stack.iter().rposition(|x| x.name == name) {
+            Some(v) => v,
+            None => return,
+        };
+        while self.stack.len() > i {
+            let frame = self.stack.pop().unwrap();
+            if frame.skip {
+                self.skip -= 1;
+                continue;
+            }
+            self.indent = frame.indent;
+            if frame.block {
+                self.line_break();
+            }
+        }
+    }
+
+    fn text(&mut self, s: &str) {
+        if self.skip > 0 {
+            return;
+        }
+        let text = decode_entities(s);
+        let starts_with_space = text.starts_with(char::is_whitespace);
+        for (i, word) in text.split_whitespace().enumerate() {
+            if (i > 0 || starts_with_space || self.pending_space) && !self.at_line_start() {
+                self.out.push(' ');
+            }
+            self.write(word);
+        }
+        self.pending_space = text.ends_with(char::is_whitespace);
+    }
+
+    fn push_frame(&mut self, name: String, skip: bool, block: bool, list: Option<ListState>) {
+        if skip {
+            self.skip += 1;
+        }
+        self.stack.push(Frame {
+            name,
+            indent: self.indent,
+            skip,
+            block,
+            example: false,
+            list,
+        });
+    }
+
+    fn write(&mut self, s: &str) {
+        if self.at_line_start() {
+            match self.pending_marker.take() {
+                Some(marker) => {
+                    let width = self.indent.saturating_sub(marker.chars().count());
+                    self.out.push_str(&" ".repeat(width));
+                    self.out.push_str(&marker);
+                }
+                None => self.out.push_str(&" ".repeat(self.indent)),
+            }
+        }
+        self.out.push_str(s);
+        self.pending_space = false;
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn line_break(&mut self) {
+        self.pending_space = false;
+        if self.at_line_start() {
+            return;
+        }
+        while self.out.ends_with(' ') || self.out.ends_with('\t') {
+            self.out.pop();
+        }
+        self.out.push('\n');
+    }
+}
+
+fn is_example(attrs: &[(String, Option<String>)]) -> bool {
+    match get_attr(attrs, "class") {
+        Some(v) => v
+            .split_whitespace()
+            .map(|x| x.to_ascii_lowercase())
+            .any(|x| EXAMPLE_CLASSES.contains(&x.as_str()) || x.contains("example")),
+        None => false,
+    }
+}
+
+/// Elements dropped together with their content by `sanitize`.
+static UNSAFE_TAGS: [&str; 9] = [
+    "applet", "base", "embed", "frame", "frameset", "iframe", "meta", "object", "script",
+];
+
+static URL_ATTRS: [&str; 10] = [
+    "action",
+    "background",
+    "data",
+    "formaction",
+    "href",
+    "poster",
+    "src",
+    "srcset",
+    "xlink:href",
+    "lowsrc",
+];
+
+/// Removes scripts, embedded frames, inline event handlers and references to
+/// external URLs from entry HTML. Links to other entries (`entry://`),
+/// sounds (`sound://`) and resources of the dictionary are kept.
+pub fn sanitize(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut dropping: Option<String> = None;
+    let mut in_style = false;
+    for token in tokenize(html) {
+        if let Some(tag) = &dropping {
+            if let Token::EndTag { name, .. } = &token {
+                if name == tag {
+                    dropping = None;
+                }
+            }
+            continue;
+        }
+        match token {
+            Token::StartTag {
+                name,
+                attrs,
+                self_closing,
+                ..
+            } => {
+                if UNSAFE_TAGS.contains(&name.as_str()) {
+                    if !self_closing && !is_void_tag(&name) {
+                        dropping = Some(name);
+                    }
+                    continue;
+                }
+                if name == "link" && get_attr(&attrs, "href").is_some_and(is_external_url) {
+                    continue;
+                }
+                in_style = name == "style" && !self_closing;
+                out.push('<');
+                out.push_str(&name);
+                for (key, value) in &attrs {
+                    if key.starts_with("on") || key == "srcdoc" {
+                        continue;
+                    }
+                    let value = match value {
+                        Some(v) => v,
+                        None => {
+                            out.push(' ');
+                            out.push_str(key);
+                            continue;
+                        }
+                    };
+                    let value = if URL_ATTRS.contains(&key.as_str()) {
+                        if key == "srcset" {
+                            if value
+                                .split(',')
+                                .any(|x| is_unsafe_url(x.split_whitespace().next().unwrap_or("")))
+                            {
+                                continue;
+                            }
+                        } else if is_unsafe_url(value) {
+                            continue;
+                        }
+                        value.to_string()
+                    } else if key == "style" {
+                        sanitize_css(value)
+                    } else {
+                        value.to_string()
+                    };
+                    out.push(' ');
+                    out.push_str(key);
+                    out.push_str("=\"");
+                    out.push_str(&escape(&value));
+                    out.push('"');
+                }
+                if self_closing {
+                    out.push_str(" /");
+                }
+                out.push('>');
+            }
+            Token::EndTag { name, raw } => {
+                if name == "style" {
+                    in_style = false;
+                }
+                if !UNSAFE_TAGS.contains(&name.as_str()) {
+                    out.push_str(raw);
+                }
+            }
+            Token::Text(s) => {
+                if in_style {
+                    out.push_str(&sanitize_css(s));
+                } else {
+                    out.push_str(s);
+                }
+            }
+            Token::Comment(_) => {}
+            Token::Doctype(s) => out.push_str(s),
+        }
+    }
+    out
+}
+
+/// Neutralizes `@import`, `expression()` and `url()` pointing outside the
+/// dictionary in a stylesheet.
+pub fn sanitize_css(css: &str) -> String {
+    let lower = css.to_ascii_lowercase();
+    if !lower.contains("url(")
+        && !lower.contains("@import")
+        && !lower.contains("expression(")
+        && !lower.contains("javascript:")
+    {
+        return css.to_string();
+    }
+    let mut out = String::with_capacity(css.len());
+    let mut i = 0;
+    while i < css.len() {
+        let rest = &lower[i..];
+        if rest.starts_with("@import") {
+            // Drop the whole rule.
+            let end = rest.find(';').map(|x| x + 1).unwrap_or(rest.len());
+            i += end;
+            continue;
+        }
+        if rest.starts_with("expression(") || rest.starts_with("javascript:") {
+            // Drop the rest of the declaration.
+            out.push_str("none");
+            i += rest.find([';', '}']).unwrap_or(rest.len());
+            continue;
+        }
+        if rest.starts_with("url(") {
+            let end = rest.find(')').map(|x| x + 1).unwrap_or(rest.len());
+            let url = css[i + 4..i + end]
+                .trim_end_matches(')')
+                .trim()
+                .trim_matches(|c| c == '"' || c == '\'');
+            if is_unsafe_url(url) {
+                out.push_str("none");
+            } else {
+                out.push_str(&css[i..i + end]);
+            }
+            i += end;
+            continue;
+        }
+        let c = css[i..].chars().next().unwrap();
+        out.push(c);
+        i += c.len_utf8();
+    }
+    out
+}
+
+fn is_external_url(url: &str) -> bool {
+    let url = url.trim().to_ascii_lowercase();
+    url.starts_with("//") || url.starts_with("\\\\") || {
+        match url.find(':') {
+            Some(i) => {
+                let scheme = &url[..i];
+                !scheme.is_empty()
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+                    && !matches!(scheme, "entry" | "sound" | "data")
+            }
+            None => false,
+        }
+    }
+}
+
+fn is_unsafe_url(url: &str) -> bool {
+    let lower: String = url
+        .trim()
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if let Some(data) = lower.strip_prefix("data:") {
+        let allowed = (data.starts_with("image/") && !data.starts_with("image/svg"))
+            || data.starts_with("audio/")
+            || data.starts_with("font/");
+        return !allowed;
+    }
+    is_external_url(&lower)
+}
@@ -4,48 +4,70 @@
 use std::{collections::HashMap, sync::Arc};
 
 use beluga_core::dictionary::NodeCache;
-use server::start_server;
-use tauri::{generate_handler, Manager, WindowEvent};
+use dict_server::start_dict_server;
+use server::{
+    bind_server, remove_port_file, start_server, EntryViewed, ENTRY_VIEWED_EVENT, SERVER_PORT_FILE,
+};
+use tauri::{generate_handler, AppHandle, Listener, Manager, WindowEvent};
 use tokio::sync::RwLock;
 #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
 use {
-    base::get_resource_directory,
-    base::recognize_text,
-    log::error,
-    ocrs::{OcrEngine, OcrEngineParams},
-    rten::Model,
-    std::str::FromStr,
-    std::sync::OnceLock,
-    tauri::Emitter,
-    tauri::{
-        menu::{Menu, MenuItem},
-        WebviewWindowBuilder,
-    },
-    tauri_plugin_global_shortcut::GlobalShortcutExt,
-    tauri_plugin_global_shortcut::Shortcut,
-    tauri_plugin_global_shortcut::ShortcutState,
+    clipboard::watch_clipboard,
+    deep_link::{handle_open_urls, handle_second_instance, launch_lookup},
+    lookup::{create_main_window, init_script},
+    ocr::{engine::load_engine, region::RegionCapture},
+    popup::show_popup,
+    settings::SETTINGS_CHANGED_EVENT,
+    shortcuts::{handle_shortcut, register_shortcuts},
+    tauri_plugin_deep_link::DeepLinkExt,
+    tray::{handle_menu_event, update_tray},
 };
 
 use handlers::{
-    add_book, add_word, delete_book, delete_words, get_book_by_id, get_book_list, get_server_port,
-    get_settings, get_word_list, import_book, open_devtools, platform, reload_dicts, resize_cache,
-    search, set_settings, set_word_familiar, update_book,
+    add_book, add_word, close_ocr_overlay, close_popup, delete_book, delete_words, get_book_by_id,
+    get_book_list, get_entry_text, get_ocr_status, get_pronunciations, get_server_port,
+    get_server_status, get_settings, get_shortcut_status, get_theme_dir, get_word_list,
+    import_book, ocr_clipboard_image, ocr_image_file, ocr_region, open_devtools, platform,
+    reload_dicts, resize_cache, search, search_entries, set_settings, set_word_familiar,
+    update_book,
 };
-use log::{debug, info, LevelFilter};
+use log::{debug, error, info, LevelFilter};
 
-use crate::{base::AppState, database::Database, settings::Settings};
+use crate::{
+    base::{get_resource_directory, AppState},
+    database::Database,
+    model::history::HistoryModel,
+    settings::Settings,
+    utils::current_timestamp,
+};
 
+mod asset_cache;
 mod base;
+pub mod cli;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+mod clipboard;
 mod database;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+mod deep_link;
+mod dict_server;
+mod entry;
 mod error;
+mod export;
 mod handlers;
+mod html;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+mod lookup;
 mod model;
+mod ocr;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+mod popup;
 mod server;
 mod settings;
-mod utils;
-
+mod shortcuts;
+mod theme;
 #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
-static OCR_ENGINE: OnceLock<OcrEngine> = OnceLock::new();
+mod tray;
+mod utils;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub async fn run() {
@@ -68,7 +90,16 @@ pub async fn run() {
         .target(tauri_plugin_log::Target::new(log_target))
         .build();
 
-    tauri::Builder::default()
+    let builder = tauri::Builder::default();
+    // A second launch, e.g. to open a link, is forwarded to the running app.
+    // It must be the first plugin.
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+    let builder = builder
+        .plugin(tauri_plugin_single_instance::init(|ah, args, _cwd| {
+            handle_second_instance(ah, args);
+        }))
+        .plugin(tauri_plugin_deep_link::init());
+    builder
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(plugin_log)
@@ -86,6 +117,14 @@ pub async fn run() {
                 };
             let cache_size = settings.config.cache_size * 1024 * 1024;
             let cache = Arc::new(RwLock::new(NodeCache::new(cache_size.into())));
+            let server_port = Some(settings.config.http_port).filter(|x| *x != 0);
+            let dict_server_port = if settings.config.dict_server {
+                Some(settings.config.dict_server_port)
+            } else {
+                None
+            };
+            #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+            let popup_lookup = settings.config.popup_lookup;
             let settings = Arc::new(RwLock::new(settings));
             let dicts = Arc::new(RwLock::new(HashMap::new()));
 
@@ -93,14 +132,96 @@ pub async fn run() {
             let settings2 = settings.clone();
             let dicts2 = dicts.clone();
             let cache2 = cache.clone();
-            let ah2 = app.app_handle().clone();
+            let resource_dir = get_resource_directory(app.app_handle().clone());
+            let port_file = data_dir.join(SERVER_PORT_FILE);
+            let ah = app.app_handle().clone();
             tokio::spawn(async move {
-                start_server(settings2, dicts2, cache2, ah2).await;
+                let listener = bind_server(Some(&ah), server_port).await;
+                let r = match listener {
+                    Ok(listener) => {
+                        start_server(
+                            settings2,
+                            dicts2,
+                            cache2,
+                            resource_dir,
+                            Some(ah),
+                            listener,
+                            Some(port_file),
+                            std::future::pending(),
+                        )
+                        .await
+                    }
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = r {
+                    error!("fail to start server. {:?}", e);
+                }
             });
 
+            if let Some(port) = dict_server_port {
+                info!("Start DICT server");
+                let settings2 = settings.clone();
+                let dicts2 = dicts.clone();
+                let cache2 = cache.clone();
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        start_dict_server(settings2, dicts2, cache2, port, std::future::pending())
+                            .await
+                    {
+                        error!("fail to start DICT server. {:?}", e);
+                    }
+                });
+            }
+
             let state = AppState::new(settings, dicts, cache);
             app.manage(state);
 
+            // The main window isn't created from the config, so a lookup the
+            // app is launched with is in the page before it's loaded.
+            info!("Create main window");
+            #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+            {
+                let ah = app.app_handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    handle_open_urls(&ah, event.urls());
+                });
+                // Schemes are registered by installers, not by AppImages or
+                // during development.
+                #[cfg(any(target_os = "windows", target_os = "linux"))]
+                if let Err(e) = app.deep_link().register_all() {
+                    error!("fail to register deep link scheme. {}", e);
+                }
+                let launch = launch_lookup(app.app_handle());
+                let script = match &launch {
+                    Some(req) if !popup_lookup => match init_script("__LOOKUP__", req) {
+                        Ok(v) => Some(v),
+                        Err(e) => {
+                            error!("fail to serialize lookup. {:?}", e);
+                            None
+                        }
+                    },
+                    _ => None,
+                };
+                if let Err(e) = create_main_window(app.app_handle(), script.as_deref()) {
+                    error!("fail to create main window. {:?}", e);
+                }
+                if let Some(req) = launch.filter(|_| popup_lookup) {
+                    if let Err(e) = show_popup(app.app_handle(), req) {
+                        error!("fail to show popup. {:?}", e);
+                    }
+                }
+            }
+            #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+            tauri::WebviewWindowBuilder::from_config(
+                app.handle(),
+                app.config()
+                    .app
+                    .windows
+                    .first()
+                    .expect("no window in config"),
+            )?
+            .build()?;
+
             info!("Load dictionaries");
             let ah = app.app_handle().clone();
             tokio::spawn(async move {
@@ -118,62 +239,64 @@ pub async fn run() {
                 let dir = ah.path().app_data_dir().unwrap();
                 let db = Database::new(dir).await;
                 ah.manage(Arc::new(db));
+                #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+                if let Err(e) = update_tray(&ah).await {
+                    error!("fail to update tray. {:?}", e);
+                }
+            });
+
+            // Log lookups except going back and forward
+            let ah = app.handle().clone();
+            app.listen(ENTRY_VIEWED_EVENT, move |event| {
+                let viewed: EntryViewed = match serde_json::from_str(event.payload()) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        error!("invalid entry_viewed event. {:?}", e);
+                        return;
+                    }
+                };
+                if viewed.nav.as_deref() == Some("history") {
+                    return;
+                }
+                let ah = ah.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = add_history(&ah, viewed).await {
+                        error!("fail to add history. {:?}", e);
+                    }
+                    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+                    if let Err(e) = update_tray(&ah).await {
+                        error!("fail to update tray. {:?}", e);
+                    }
+                });
             });
 
             #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
             {
                 info!("Init tray");
-                let main_menu_item = MenuItem::with_id(app, "main", "Beluga", true, None::<&str>)
-                    .expect("fail to create main menu item");
-                let quit_menu_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)
-                    .expect("fail to create quit menu item");
-                let menu = Menu::with_items(app, &[&main_menu_item, &quit_menu_item])
-                    .expect("fail to create menu");
                 let tray = app.tray_by_id("main").expect("no tray setting");
-                tray.set_menu(Some(menu)).expect("fail to set menu");
-                tray.on_menu_event(|app, event| match event.id.as_ref() {
-                    "main" => {
-                        if let Some(win) = app.get_webview_window("main") {
-                            let _ = win.set_focus();
-                        } else {
-                            WebviewWindowBuilder::from_config(
-                                app,
-                                &app.config()
-                                    .app
-                                    .windows
-                                    .get(0)
-                                    .expect("no window in config")
-                                    .clone(),
-                            )
-                            .unwrap()
-                            .build()
-                            .expect("fail to create main window")
-                            .show()
-                            .expect("fail to show main window");
-
-                            #[cfg(target_os = "macos")]
-                            let _ = app.set_activation_policy(tauri::ActivationPolicy::Regular);
-                        };
-                    }
-                    "quit" => {
-                        std::process::exit(0);
-                    }
-                    _ => {}
+                tray.on_menu_event(|ah, event| handle_menu_event(ah, event.id.as_ref()));
+                // Recent lookups and the checked items follow the settings.
+                let ah = app.app_handle().clone();
+                app.listen(SETTINGS_CHANGED_EVENT, move |_| {
+                    let ah = ah.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = update_tray(&ah).await {
+                            error!("fail to update tray. {:?}", e);
+                        }
+                    });
                 });
-
-                info!("Register global shortcuts");
                 let ah = app.app_handle().clone();
                 tokio::spawn(async move {
-                    let state = ah.state::<AppState>();
-                    let settings_lock = state.settings.read().await;
-                    debug!("{}", settings_lock.config.ocr_shortcut);
-                    if let Ok(v) = Shortcut::from_str(&settings_lock.config.ocr_shortcut) {
-                        if let Err(e) = ah.global_shortcut().register(v) {
-                            error!("fail to register ocr shortcut. {}", e);
-                        }
+                    if let Err(e) = update_tray(&ah).await {
+                        error!("fail to update tray. {:?}", e);
                     }
                 });
 
+                app.handle()
+                    .plugin(tauri_plugin_clipboard_manager::init())
+                    .expect("fail to load tauri_plugin_clipboard_manager");
+                app.manage(RegionCapture::default());
+
                 app.handle()
                     .plugin(
                         tauri_plugin_global_shortcut::Builder::new()
@@ -181,88 +304,39 @@ pub async fn run() {
                                 debug!("{:?}", shortcut);
                                 let ah = ah.clone();
                                 let sc = *shortcut;
+                                let state = event.state();
                                 tokio::spawn(async move {
-                                    let state = ah.state::<AppState>();
-                                    let settings_lock = state.settings.read().await;
-                                    if let Ok(sc_ocr) =
-                                        Shortcut::from_str(&settings_lock.config.ocr_shortcut)
-                                    {
-                                        if sc != sc_ocr {
-                                            return;
-                                        }
-                                        match event.state() {
-                                            ShortcutState::Pressed => {
-                                                let engine = if let Some(eg) = OCR_ENGINE.get() {
-                                                    eg
-                                                } else {
-                                                    return;
-                                                };
-                                                let ocr_width = settings_lock.config.ocr_width;
-                                                let ocr_height = settings_lock.config.ocr_height;
-                                                let text =
-                                                    recognize_text(engine, ocr_width, ocr_height);
-                                                debug!("recognized text: {}", text);
-                                                if let Some(win) = ah.get_webview_window("main") {
-                                                    if let Err(e) = win.set_focus() {
-                                                        error!("fail to focus window. {}", e);
-                                                    }
-                                                    let _ = ah.emit("ocr_text", text);
-                                                } else {
-                                                    let script = format!(
-                                                        "window.__OCR_TEXT__ = \"{}\"",
-                                                        text
-                                                    );
-                                                    WebviewWindowBuilder::from_config(
-                                                        &ah,
-                                                        &ah.config()
-                                                            .app
-                                                            .windows
-                                                            .get(0)
-                                                            .expect("no window in config")
-                                                            .clone(),
-                                                    )
-                                                    .expect("fail to build window from config")
-                                                    .initialization_script(&script)
-                                                    .build()
-                                                    .expect("fail to build window")
-                                                    .show()
-                                                    .expect("fail to show window");
-
-                                                    #[cfg(target_os = "macos")]
-                                                    let _ = ah.set_activation_policy(
-                                                        tauri::ActivationPolicy::Regular,
-                                                    );
-                                                }
-                                            }
-                                            ShortcutState::Released => {}
-                                        }
-                                    };
+                                    handle_shortcut(&ah, sc, state).await;
                                 });
                             })
                             .build(),
                     )
                     .expect("fail to load tauri_plugin_global_shortcut");
 
-                let _ = OCR_ENGINE.get_or_init(|| {
-                    info!("Load OCR engine");
-                    let ah = app.app_handle();
-                    let resource_dir = get_resource_directory(ah.clone());
-                    let detection_model_path = resource_dir.join("text-detection.rten");
-                    let rec_model_path = resource_dir.join("text-recognition.rten");
-                    let detection_model = Model::load_file(detection_model_path)
-                        .expect("fail to load detection model");
-                    let recognition_model =
-                        Model::load_file(rec_model_path).expect("fail to load recognition model");
-                    match OcrEngine::new(OcrEngineParams {
-                        detection_model: Some(detection_model),
-                        recognition_model: Some(recognition_model),
-                        ..Default::default()
-                    }) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            panic!("fail to load OCR engine. {}", e);
-                        }
-                    }
+                info!("Register global shortcuts");
+                let ah = app.app_handle().clone();
+                tokio::spawn(async move {
+                    let state = ah.state::<AppState>();
+                    let shortcuts = state.settings.read().await.config.active_shortcuts();
+                    register_shortcuts(&ah, &shortcuts);
+                });
+                watch_clipboard(app.app_handle().clone());
+
+                // Without models OCR is disabled and the status tells why.
+                let ah = app.app_handle().clone();
+                tokio::spawn(async move {
+                    let (detection_model, recognition_model) = {
+                        let state = ah.state::<AppState>();
+                        let settings_lock = state.settings.read().await;
+                        (
+                            settings_lock.config.ocr_detection_model.clone(),
+                            settings_lock.config.ocr_recognition_model.clone(),
+                        )
+                    };
+                    let _ = tokio::task::spawn_blocking(move || {
+                        load_engine(&ah, &detection_model, &recognition_model)
+                    })
+                    .await;
                 });
             }
 
@@ -286,10 +360,22 @@ pub async fn run() {
             platform,
             open_devtools,
             get_server_port,
+            get_server_status,
             search,
+            get_entry_text,
+            search_entries,
+            get_pronunciations,
+            ocr_region,
+            close_ocr_overlay,
+            close_popup,
+            get_ocr_status,
+            get_shortcut_status,
+            ocr_image_file,
+            ocr_clipboard_image,
             resize_cache,
             get_settings,
             set_settings,
+            get_theme_dir,
             reload_dicts,
             get_book_list,
             get_book_by_id,
@@ -304,10 +390,49 @@ pub async fn run() {
         ])
         .build(tauri::generate_context!())
         .expect("error while running application")
-        .run(|_ah, event| match event {
-            tauri::RunEvent::ExitRequested { api, .. } => {
+        .run(|ah, event| match event {
+            // Closing the windows keeps the app in the tray, only
+            // `AppHandle::exit` quits.
+            tauri::RunEvent::ExitRequested {
+                code: None, api, ..
+            } => {
                 api.prevent_exit();
             }
+            tauri::RunEvent::Exit => {
+                if let Ok(dir) = ah.path().app_data_dir() {
+                    remove_port_file(&dir.join(SERVER_PORT_FILE));
+                }
+            }
             _ => {}
         });
 }
+
+async fn add_history(ah: &AppHandle, viewed: EntryViewed) -> error::Result<()> {
+    // The database may not be ready right after startup
+    let db = match ah.try_state::<Arc<Database>>() {
+        Some(v) => v.inner().clone(),
+        None => return Ok(()),
+    };
+    let dict = {
+        let state = ah.state::<AppState>();
+        let settings_lock = state.settings.read().await;
+        match settings_lock
+            .config
+            .dicts
+            .iter()
+            .find(|x| x.id == viewed.dict_id)
+        {
+            Some(v) => v.name.clone(),
+            None => return Ok(()),
+        }
+    };
+    let mut conn = db.pool.acquire().await?;
+    let mut history = HistoryModel {
+        id: 0,
+        dict,
+        name: viewed.name,
+        create_time: current_timestamp(),
+    };
+    history.insert(&mut conn).await?;
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use anyhow::{anyhow, Result};
+use log::error;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindowBuilder};
+
+use crate::{base::AppState, popup::show_popup};
+
+/// Sent to the main window with a word to look up
+pub static LOOKUP_EVENT: &str = "lookup";
+
+/// A word to look up, with the name of the dictionary to show it from first
+#[derive(Debug, Clone, Serialize)]
+pub struct LookupRequest {
+    pub text: String,
+    pub dict: Option<String>,
+}
+
+/// Looks up a word in the popup window if it's enabled, otherwise in the
+/// main window.
+pub async fn lookup(ah: &AppHandle, text: &str, dict: Option<&str>) -> Result<()> {
+    let req = LookupRequest {
+        text: text.to_string(),
+        dict: dict.map(|x| x.to_string()),
+    };
+    let state = ah.state::<AppState>();
+    if state.settings.read().await.config.popup_lookup {
+        return show_popup(ah, req);
+    }
+    send_to_main(ah, LOOKUP_EVENT, "__LOOKUP__", req)
+}
+
+/// Sends an event to the main window and focuses it. A closed window can't
+/// receive events before it's loaded, so it's created with the payload
+/// assigned to `window[global]` instead.
+pub fn send_to_main<T: Serialize + Clone>(
+    ah: &AppHandle,
+    event: &str,
+    global: &str,
+    payload: T,
+) -> Result<()> {
+    if let Some(win) = ah.get_webview_window("main") {
+        if let Err(e) = win.show() {
+            error!("fail to show window. {}", e);
+        }
+        if let Err(e) = win.set_focus() {
+            error!("fail to focus window. {}", e);
+        }
+        ah.emit_to("main", event, payload)?;
+        return Ok(());
+    }
+    create_main_window(ah, Some(&init_script(global, &payload)?))
+}
+
+/// Script assigning the payload to `window[global]`
+pub fn init_script<T: Serialize>(global: &str, payload: &T) -> Result<String> {
+    // Serialized JSON is a valid JavaScript expression.
+    Ok(format!(
+        "window.{} = {}",
+        global,
+        serde_json::to_string(payload)?
+    ))
+}
+
+/// Shows and focuses the main window, creating it if it's closed.
+pub fn show_main_window(ah: &AppHandle) -> Result<()> {
+    match ah.get_webview_window("main") {
+        Some(win) => {
+            win.show()?;
+            win.set_focus()?;
+        }
+        None => create_main_window(ah, None)?,
+    }
+    Ok(())
+}
+
+/// Hides the main window if it's focused, otherwise shows it.
+pub fn toggle_main_window(ah: &AppHandle) -> Result<()> {
+    match ah.get_webview_window("main") {
+        Some(win) if win.is_visible()? && win.is_focused()? => win.hide()?,
+        Some(win) => {
+            win.show()?;
+            win.set_focus()?;
+        }
+        None => create_main_window(ah, None)?,
+    }
+    Ok(())
+}
+
+/// Creates the main window from the config, running `script` before the page
+/// is loaded.
+pub fn create_main_window(ah: &AppHandle, script: Option<&str>) -> Result<()> {
+    let config = ah
+        .config()
+        .app
+        .windows
+        .first()
+        .ok_or_else(|| anyhow!("no window in config"))?
+        .clone();
+    let mut builder = WebviewWindowBuilder::from_config(ah, &config)?;
+    if let Some(v) = script {
+        builder = builder.initialization_script(v);
+    }
+    builder.build()?.show()?;
+    #[cfg(target_os = "macos")]
+    let _ = ah.set_activation_policy(tauri::ActivationPolicy::Regular);
+    Ok(())
+}
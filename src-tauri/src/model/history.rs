@@ -0,0 +1,50 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqliteConnection};
+
+use super::RowID;
+use crate::settings::DictItem;
+
+pub const HISTORY_TABLE: &str = "history";
+
+#[derive(FromRow, Clone, Serialize, Deserialize)]
+pub struct HistoryModel {
+    pub id: RowID,
+    /// Name of the dictionary, ids change when dictionaries are reloaded
+    pub dict: String,
+    pub name: String,
+    pub create_time: i64,
+}
+
+impl HistoryModel {
+    pub async fn insert(&mut self, conn: &mut SqliteConnection) -> Result<i64> {
+        let sql = format!(
+            "INSERT INTO {}(dict, name, create_time) VALUES(?, ?, ?)",
+            HISTORY_TABLE
+        );
+        let id = sqlx::query(&sql)
+            .bind(&self.dict)
+            .bind(&self.name)
+            .bind(&self.create_time)
+            .execute(conn)
+            .await?
+            .last_insert_rowid();
+        self.id = id;
+        Ok(id)
+    }
+
+    /// The id of the dictionary if it's still loaded
+    pub fn dict_id(&self, dicts: &[DictItem]) -> Option<u32> {
+        dicts.iter().find(|x| x.name == self.dict).map(|x| x.id)
+    }
+
+    /// Returns the latest lookups, newest first.
+    pub async fn list(conn: &mut SqliteConnection, size: usize) -> Result<Vec<HistoryModel>> {
+        let sql = format!(
+            "SELECT * FROM {} ORDER BY create_time DESC, id DESC LIMIT {}",
+            HISTORY_TABLE, size
+        );
+        let list: Vec<HistoryModel> = sqlx::query_as(&sql).fetch_all(conn).await?;
+        Ok(list)
+    }
+}
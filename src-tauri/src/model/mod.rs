@@ -1,4 +1,5 @@
 pub mod book;
+pub mod history;
 pub mod word;
 
 pub type RowID = i64;
@@ -14,12 +14,14 @@ pub struct WordModel {
     pub familiar: u32,
     pub book_id: RowID,
     pub create_time: i64,
+    /// The sentence the word was looked up in
+    pub context: String,
 }
 
 impl WordModel {
     pub async fn insert(&mut self, conn: &mut SqliteConnection) -> Result<i64> {
         let sql = format!(
-            "INSERT INTO {}(name, familiar, book_id, create_time) VALUES(?, ?, ?, ?)",
+            "INSERT INTO {}(name, familiar, book_id, create_time, context) VALUES(?, ?, ?, ?, ?)",
             WORD_TABLE
         );
         let id = sqlx::query(&sql)
@@ -27,6 +29,7 @@ impl WordModel {
             .bind(&self.familiar)
             .bind(&self.book_id)
             .bind(&self.create_time)
+            .bind(&self.context)
             .execute(conn)
             .await?
             .last_insert_rowid();
@@ -39,7 +42,7 @@ impl WordModel {
             return Ok(());
         }
         let sql = format!(
-            "INSERT INTO {}(name, familiar, book_id, create_time) ",
+            "INSERT INTO {}(name, familiar, book_id, create_time, context) ",
             WORD_TABLE
         );
         let chunks = list.chunks(1000);
@@ -49,7 +52,8 @@ impl WordModel {
                 b.push_bind(&item.name)
                     .push_bind(&item.familiar)
                     .push_bind(&item.book_id)
-                    .push_bind(&item.create_time);
+                    .push_bind(&item.create_time)
+                    .push_bind(&item.context);
             });
             qb.push(" ON CONFLICT(book_id, name) DO NOTHING");
             qb.build().execute(&mut *conn).await?;
@@ -0,0 +1,136 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use anyhow::Result;
+use image::{DynamicImage, Rgb, RgbImage};
+use tauri::{AppHandle, Manager};
+
+use super::{OcrHit, OcrLine, OcrWord};
+use crate::{base::AppState, utils::current_timestamp};
+
+static DEBUG_DIR: &str = "ocr";
+static WORD_COLOR: Rgb<u8> = Rgb([0, 200, 0]);
+static HIT_COLOR: Rgb<u8> = Rgb([255, 0, 0]);
+/// Captures kept in the folder, older ones are removed
+static MAX_CAPTURES: usize = 100;
+/// Tells apart captures saved in the same millisecond
+static CAPTURE_SEQ: AtomicU32 = AtomicU32::new(0);
+
+/// The folder to save OCR captures in, if the debug setting is on
+pub async fn debug_directory(ah: &AppHandle) -> Option<PathBuf> {
+    let state = ah.state::<AppState>();
+    if !state.settings.read().await.config.ocr_debug {
+        return None;
+    }
+    ah.path().app_log_dir().ok().map(|x| x.join(DEBUG_DIR))
+}
+
+/// Saves the image fed to OCR and a copy with the rects of the recognized
+/// words drawn on it, the hit word in red. Only the latest `MAX_CAPTURES`
+/// are kept.
+pub fn save_capture(
+    dir: &Path,
+    img: &DynamicImage,
+    lines: &[OcrLine],
+    hit: Option<&OcrHit>,
+) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let name = format!(
+        "{}-{}",
+        current_timestamp(),
+        CAPTURE_SEQ.fetch_add(1, Ordering::Relaxed)
+    );
+    img.save(dir.join(format!("{}.png", name)))?;
+    let mut overlay = img.to_rgb8();
+    for (i, line) in lines.iter().enumerate() {
+        for (j, wd) in line.words.iter().enumerate() {
+            let color = if hit.is_some_and(|x| x.line == i && x.word == j) {
+                HIT_COLOR
+            } else {
+                WORD_COLOR
+            };
+            draw_rect(&mut overlay, wd, color);
+        }
+    }
+    overlay.save(dir.join(format!("{}-rects.png", name)))?;
+    remove_old_captures(dir)
+}
+
+/// Removes the oldest captures beyond `MAX_CAPTURES`. Captures are named
+/// `{timestamp}-{seq}.png`, other files are left alone.
+fn remove_old_captures(dir: &Path) -> Result<()> {
+    let mut names: Vec<(i64, u32)> = fs::read_dir(dir)?
+        .filter_map(|x| x.ok())
+        .filter_map(|x| {
+            let name = x.file_name().into_string().ok()?;
+            let (ts, seq) = name.strip_suffix(".png")?.split_once('-')?;
+            Some((ts.parse().ok()?, seq.parse().ok()?))
+        })
+        .collect();
+    if names.len() <= MAX_CAPTURES {
+        return Ok(());
+    }
+    names.sort_unstable();
+    for (ts, seq) in &names[..names.len() - MAX_CAPTURES] {
+        fs::remove_file(dir.join(format!("{}-{}.png", ts, seq)))?;
+        let _ = fs::remove_file(dir.join(format!("{}-{}-rects.png", ts, seq)));
+    }
+    Ok(())
+}
+
+fn draw_rect(img: &mut RgbImage, wd: &OcrWord, color: Rgb<u8>) {
+    let (w, h) = img.dimensions();
+    if w == 0 || h == 0 {
+        return;
+    }
+    let clamp_x = |v: i32| v.clamp(0, w as i32 - 1) as u32;
+    let clamp_y = |v: i32| v.clamp(0, h as i32 - 1) as u32;
+    let (x1, x2) = (clamp_x(wd.x), clamp_x(wd.x + wd.width));
+    let (y1, y2) = (clamp_y(wd.y), clamp_y(wd.y + wd.height));
+    for x in x1..=x2 {
+        img.put_pixel(x, y1, color);
+        img.put_pixel(x, y2, color);
+    }
+    for y in y1..=y2 {
+        img.put_pixel(x1, y, color);
+        img.put_pixel(x2, y, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_latest_captures() {
+        let dir = std::env::temp_dir().join(format!("beluga-ocr-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let total = MAX_CAPTURES + 5;
+        for i in 0..total {
+            // Two captures per millisecond, the sequence decides the order.
+            let name = format!("{}-{}", 1_000 + i / 2, i);
+            fs::write(dir.join(format!("{}.png", name)), b"").unwrap();
+            fs::write(dir.join(format!("{}-rects.png", name)), b"").unwrap();
+        }
+        fs::write(dir.join("notes.png"), b"").unwrap();
+        remove_old_captures(&dir).unwrap();
+
+        let mut files: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|x| x.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files.len(), MAX_CAPTURES * 2 + 1);
+        assert!(files.contains(&"notes.png".to_string()));
+        for i in 0..total {
+            let name = format!("{}-{}", 1_000 + i / 2, i);
+            let kept = i >= total - MAX_CAPTURES;
+            assert_eq!(files.contains(&format!("{}.png", name)), kept);
+            assert_eq!(files.contains(&format!("{}-rects.png", name)), kept);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use ocrs::{OcrEngine, OcrEngineParams};
+use rten::Model;
+use tauri::{AppHandle, Emitter};
+
+use super::{OcrStatus, OCR_STATUS_EVENT};
+use crate::base::get_resource_directory;
+
+static DETECTION_MODEL_FILE: &str = "text-detection.rten";
+static RECOGNITION_MODEL_FILE: &str = "text-recognition.rten";
+
+/// The engine in use, replaced when other models are configured
+static ENGINE: RwLock<Option<Arc<OcrEngine>>> = RwLock::new(None);
+static STATUS: RwLock<Option<OcrStatus>> = RwLock::new(None);
+
+/// Returns the loaded engine. Callers keep using the engine they got if it's
+/// replaced in the meantime.
+pub fn current_engine() -> Result<Arc<OcrEngine>> {
+    ENGINE
+        .read()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| anyhow!("OCR engine is not loaded"))
+}
+
+pub fn ocr_status() -> OcrStatus {
+    STATUS.read().unwrap().clone().unwrap_or_default()
+}
+
+/// Resolves a configured model file, an empty one is the bundled model.
+fn model_file(ah: &AppHandle, configured: &str, bundled: &str) -> PathBuf {
+    if configured.is_empty() {
+        get_resource_directory(ah.clone()).join(bundled)
+    } else {
+        PathBuf::from(configured)
+    }
+}
+
+fn load_model(file: &Path) -> Result<Model> {
+    if !file.is_file() {
+        return Err(anyhow!("no model file {}", file.display()));
+    }
+    Model::load_file(file).map_err(|e| anyhow!("fail to load {}. {}", file.display(), e))
+}
+
+/// Loads the models and switches to the new engine. If they can't be loaded,
+/// OCR is disabled until other models are configured. This is slow, call it
+/// on a blocking thread.
+pub fn load_engine(ah: &AppHandle, detection_model: &str, recognition_model: &str) {
+    info!("Load OCR engine");
+    let detection_file = model_file(ah, detection_model, DETECTION_MODEL_FILE);
+    let recognition_file = model_file(ah, recognition_model, RECOGNITION_MODEL_FILE);
+    let engine = load_model(&detection_file).and_then(|detection| {
+        let recognition = load_model(&recognition_file)?;
+        OcrEngine::new(OcrEngineParams {
+            detection_model: Some(detection),
+            recognition_model: Some(recognition),
+            ..Default::default()
+        })
+        .map_err(|e| anyhow!("fail to create OCR engine. {}", e))
+    });
+    let status = match engine {
+        Ok(v) => {
+            *ENGINE.write().unwrap() = Some(Arc::new(v));
+            OcrStatus {
+                loaded: true,
+                error: None,
+            }
+        }
+        Err(e) => {
+            error!("fail to load OCR engine. {:?}", e);
+            ENGINE.write().unwrap().take();
+            OcrStatus {
+                loaded: false,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+    *STATUS.write().unwrap() = Some(status.clone());
+    if let Err(e) = ah.emit(OCR_STATUS_EVENT, status) {
+        error!("fail to notify {}. {}", OCR_STATUS_EVENT, e);
+    }
+}
@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub mod debug;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub mod engine;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub mod preprocess;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub mod recognize;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub mod region;
+
+/// Sent to the main window with an `OcrResult`
+pub static OCR_RESULT_EVENT: &str = "ocr_result";
+/// Sent with an `OcrStatus` when the engine is loaded or fails to load
+pub static OCR_STATUS_EVENT: &str = "ocr_status";
+
+/// Whether OCR can be used. Neither loaded nor failed while the models are
+/// being loaded.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OcrStatus {
+    pub loaded: bool,
+    pub error: Option<String>,
+}
+
+/// A rect selected in the OCR overlay, in CSS pixels of a view of the given
+/// size
+#[derive(Debug, Deserialize)]
+pub struct OcrRegion {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub view_width: f64,
+    pub view_height: f64,
+}
+
+/// A recognized word and its bounding box in pixels of the recognized image
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrWord {
+    pub text: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrLine {
+    pub text: String,
+    pub words: Vec<OcrWord>,
+}
+
+impl OcrLine {
+    pub fn new(words: Vec<OcrWord>) -> Self {
+        let text = words
+            .iter()
+            .map(|x| x.text.as_str())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        Self { text, words }
+    }
+}
+
+/// Hyphens that break a word at the end of a line
+static LINE_BREAK_HYPHENS: [char; 3] = ['-', '\u{2010}', '\u{00AD}'];
+
+impl OcrWord {
+    /// Distance from a point to the bounding box, 0 if the box contains it
+    fn distance(&self, x: f32, y: f32) -> f32 {
+        let left = self.x as f32;
+        let top = self.y as f32;
+        let right = (self.x + self.width) as f32;
+        let bottom = (self.y + self.height) as f32;
+        let dx = (left - x).max(x - right).max(0.0);
+        let dy = (top - y).max(y - bottom).max(0.0);
+        (dx * dx + dy * dy).sqrt()
+    }
+}
+
+/// A word in `OcrResult::lines`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OcrHit {
+    pub line: usize,
+    pub word: usize,
+    /// The word to look up, without punctuation and joined with the other
+    /// half if it's hyphenated across lines
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrResult {
+    /// Lines joined by line breaks
+    pub text: String,
+    pub lines: Vec<OcrLine>,
+    /// The word under the mouse pointer in cursor mode
+    pub hit: Option<OcrHit>,
+}
+
+impl OcrResult {
+    pub fn new(lines: Vec<OcrLine>) -> Self {
+        let text = lines
+            .iter()
+            .map(|x| x.text.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n");
+        Self {
+            text,
+            lines,
+            hit: None,
+        }
+    }
+}
+
+/// Finds the word whose bounding box contains a point. If the point is
+/// between words, the nearest word within `tolerance` pixels is taken.
+pub fn hit_test(lines: &[OcrLine], x: f32, y: f32, tolerance: f32) -> Option<OcrHit> {
+    let mut nearest: Option<(f32, usize, usize)> = None;
+    for (i, line) in lines.iter().enumerate() {
+        for (j, wd) in line.words.iter().enumerate() {
+            let d = wd.distance(x, y);
+            if d <= tolerance && nearest.is_none_or(|(v, _, _)| d < v) {
+                nearest = Some((d, i, j));
+            }
+        }
+    }
+    let (_, line, word) = nearest?;
+    Some(OcrHit {
+        line,
+        word,
+        text: lookup_text(lines, line, word),
+    })
+}
+
+/// Text of a word for lookup. A word hyphenated at the end of a line is
+/// joined with the first word of the next line, and the other way round.
+fn lookup_text(lines: &[OcrLine], line: usize, word: usize) -> String {
+    let words = &lines[line].words;
+    let text = &words[word].text;
+    if word + 1 == words.len() && text.ends_with(LINE_BREAK_HYPHENS) {
+        if let Some(next) = lines.get(line + 1).and_then(|x| x.words.first()) {
+            return clean_word(&format!(
+                "{}{}",
+                text.trim_end_matches(LINE_BREAK_HYPHENS),
+                next.text
+            ));
+        }
+    }
+    if word == 0 && line > 0 {
+        if let Some(prev) = lines[line - 1].words.last() {
+            if prev.text.ends_with(LINE_BREAK_HYPHENS) {
+                return clean_word(&format!(
+                    "{}{}",
+                    prev.text.trim_end_matches(LINE_BREAK_HYPHENS),
+                    text
+                ));
+            }
+        }
+    }
+    clean_word(text)
+}
+
+/// Strips leading and trailing punctuation, keeping the inner one as in
+/// "don't" or "e-mail".
+pub fn clean_word(text: &str) -> String {
+    text.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(words: &[(&str, i32, i32)]) -> OcrLine {
+        OcrLine::new(
+            words
+                .iter()
+                .map(|&(text, x, y)| OcrWord {
+                    text: text.to_string(),
+                    x,
+                    y,
+                    width: 40,
+                    height: 20,
+                })
+                .collect(),
+        )
+    }
+
+    fn sample() -> Vec<OcrLine> {
+        vec![
+            line(&[("Hello,", 0, 0), ("(world)", 50, 0), ("exam-", 100, 0)]),
+            line(&[("ple.", 0, 30), ("don't", 50, 30), ("stop", 100, 30)]),
+        ]
+    }
+
+    #[test]
+    fn hit_inside_word() {
+        let hit = hit_test(&sample(), 60.0, 10.0, 0.0).unwrap();
+        assert_eq!((hit.line, hit.word), (0, 1));
+        assert_eq!(hit.text, "world");
+    }
+
+    #[test]
+    fn hit_nearest_word_in_gap() {
+        // Between "Hello," (ends at 40) and "(world)" (starts at 50)
+        let hit = hit_test(&sample(), 47.0, 10.0, 5.0).unwrap();
+        assert_eq!((hit.line, hit.word), (0, 1));
+        let hit = hit_test(&sample(), 42.0, 10.0, 5.0).unwrap();
+        assert_eq!((hit.line, hit.word), (0, 0));
+        assert_eq!(hit.text, "Hello");
+    }
+
+    #[test]
+    fn hit_nothing_beyond_tolerance() {
+        assert_eq!(hit_test(&sample(), 45.0, 10.0, 2.0), None);
+        assert_eq!(hit_test(&sample(), 300.0, 300.0, 20.0), None);
+        assert_eq!(hit_test(&[], 0.0, 0.0, 20.0), None);
+    }
+
+    #[test]
+    fn hit_joins_hyphenated_word() {
+        let lines = sample();
+        let hit = hit_test(&lines, 110.0, 10.0, 0.0).unwrap();
+        assert_eq!(hit.text, "example");
+        let hit = hit_test(&lines, 10.0, 40.0, 0.0).unwrap();
+        assert_eq!((hit.line, hit.word), (1, 0));
+        assert_eq!(hit.text, "example");
+    }
+
+    #[test]
+    fn hyphen_without_next_line_is_stripped() {
+        let lines = vec![line(&[("end-", 0, 0)])];
+        assert_eq!(hit_test(&lines, 10.0, 10.0, 0.0).unwrap().text, "end");
+    }
+
+    #[test]
+    fn clean_word_keeps_inner_punctuation() {
+        assert_eq!(clean_word("don't"), "don't");
+        assert_eq!(clean_word("\"e-mail\"."), "e-mail");
+        assert_eq!(clean_word("...!"), "");
+        assert_eq!(clean_word("café,"), "café");
+    }
+}
@@ -0,0 +1,115 @@
+use image::{imageops::FilterType, DynamicImage, GrayImage};
+use tauri::{AppHandle, Manager};
+
+use crate::{base::AppState, settings::Configuration};
+
+/// Images lower than this are upscaled, text of small crops is often too
+/// small for the recognition model.
+static UPSCALE_MIN_HEIGHT: u32 = 160;
+static UPSCALE_MAX_FACTOR: u32 = 3;
+/// Share of the darkest and the brightest pixels ignored when stretching
+/// the contrast
+static CONTRAST_CLIP: f32 = 0.01;
+
+/// Stages applied to an image before it's recognized
+#[derive(Debug, Clone, Copy)]
+pub struct Preprocess {
+    pub grayscale: bool,
+    /// Inverts images with a dark background, the models do better on dark
+    /// text on a light background
+    pub auto_invert: bool,
+    pub upscale: bool,
+    pub normalize_contrast: bool,
+    /// Scales the area around the mouse pointer by the monitor scale factor
+    pub dpi_aware_crop: bool,
+}
+
+impl Preprocess {
+    pub fn from_config(config: &Configuration) -> Self {
+        Self {
+            grayscale: config.ocr_grayscale,
+            auto_invert: config.ocr_auto_invert,
+            upscale: config.ocr_upscale,
+            normalize_contrast: config.ocr_normalize_contrast,
+            dpi_aware_crop: config.ocr_dpi_aware_crop,
+        }
+    }
+
+    pub async fn from_settings(ah: &AppHandle) -> Self {
+        let state = ah.state::<AppState>();
+        let settings_lock = state.settings.read().await;
+        Self::from_config(&settings_lock.config)
+    }
+}
+
+/// Applies the stages and returns the image with the factor it's scaled by.
+pub fn preprocess(img: &DynamicImage, opts: Preprocess) -> (DynamicImage, u32) {
+    let mut img = if opts.grayscale {
+        DynamicImage::ImageLuma8(img.to_luma8())
+    } else {
+        DynamicImage::ImageRgb8(img.to_rgb8())
+    };
+    if opts.auto_invert && mean(&img.to_luma8()) < 128.0 {
+        img.invert();
+    }
+    if opts.normalize_contrast {
+        let luma = img.to_luma8();
+        stretch_contrast(&mut img, &luma);
+    }
+    let mut factor = 1;
+    if opts.upscale && img.height() > 0 && img.height() < UPSCALE_MIN_HEIGHT {
+        factor = UPSCALE_MIN_HEIGHT
+            .div_ceil(img.height())
+            .min(UPSCALE_MAX_FACTOR);
+        img = img.resize_exact(
+            img.width() * factor,
+            img.height() * factor,
+            FilterType::CatmullRom,
+        );
+    }
+    (img, factor)
+}
+
+fn mean(luma: &GrayImage) -> f32 {
+    let count = luma.as_raw().len();
+    if count == 0 {
+        return 255.0;
+    }
+    luma.as_raw().iter().map(|&x| x as u64).sum::<u64>() as f32 / count as f32
+}
+
+/// Stretches the levels linearly so the clipped darkest and brightest levels
+/// become black and white.
+fn stretch_contrast(img: &mut DynamicImage, luma: &GrayImage) {
+    let mut histogram = [0usize; 256];
+    for &v in luma.as_raw() {
+        histogram[v as usize] += 1;
+    }
+    let clip = (luma.as_raw().len() as f32 * CONTRAST_CLIP) as usize;
+    let low = level_at(histogram.iter().enumerate(), clip).unwrap_or(0);
+    let high = level_at(histogram.iter().enumerate().rev(), clip).unwrap_or(255);
+    if high <= low {
+        return;
+    }
+    let range = (high - low) as f32;
+    let map = |v: &mut u8| {
+        *v = ((*v as f32 - low as f32) / range * 255.0).clamp(0.0, 255.0) as u8;
+    };
+    match img {
+        DynamicImage::ImageLuma8(v) => v.iter_mut().for_each(map),
+        DynamicImage::ImageRgb8(v) => v.iter_mut().for_each(map),
+        _ => {}
+    }
+}
+
+/// The first level at which the accumulated count exceeds `clip`
+fn level_at<'a>(levels: impl Iterator<Item = (usize, &'a usize)>, clip: usize) -> Option<u8> {
+    let mut sum = 0;
+    for (level, count) in levels {
+        sum += count;
+        if sum > clip {
+            return Some(level as u8);
+        }
+    }
+    None
+}
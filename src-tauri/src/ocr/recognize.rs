@@ -0,0 +1,229 @@
+use std::{
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use log::{debug, error};
+use mouse_position::mouse_position::Mouse;
+use ocrs::{ImageSource, OcrEngine, TextItem};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use super::{
+    debug::{debug_directory, save_capture},
+    engine::current_engine,
+    hit_test,
+    preprocess::{preprocess, Preprocess},
+    OcrLine, OcrResult, OcrWord,
+};
+use crate::base::AppState;
+
+/// How far in pixels the mouse pointer can be from a word to hit it
+static HIT_TOLERANCE: f32 = 16.0;
+
+/// Incremented by every cursor OCR. A running one whose number is outdated
+/// stops at the next stage and its result is dropped, as the inference
+/// itself can't be interrupted.
+static CURSOR_OCR_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Recognizes the text around the mouse pointer on the blocking pool, with
+/// the word under the pointer as the hit. Returns `None` if it's cancelled by
+/// another cursor OCR.
+pub async fn ocr_at_cursor(ah: &AppHandle) -> Result<Option<OcrResult>> {
+    let seq = CURSOR_OCR_SEQ.fetch_add(1, Ordering::SeqCst) + 1;
+    let cancelled = move || CURSOR_OCR_SEQ.load(Ordering::SeqCst) != seq;
+    let (w, h, opts) = {
+        let state = ah.state::<AppState>();
+        let settings_lock = state.settings.read().await;
+        let config = &settings_lock.config;
+        (
+            config.ocr_width,
+            config.ocr_height,
+            Preprocess::from_config(config),
+        )
+    };
+    let debug_dir = debug_directory(ah).await;
+    let engine = current_engine()?;
+    let result = tokio::task::spawn_blocking(move || {
+        recognize_at_cursor(&engine, w, h, opts, &cancelled, debug_dir.as_deref())
+    })
+    .await??;
+    if cancelled() {
+        return Ok(None);
+    }
+    Ok(result)
+}
+
+fn recognize_at_cursor(
+    engine: &OcrEngine,
+    w: u32,
+    h: u32,
+    opts: Preprocess,
+    cancelled: &dyn Fn() -> bool,
+    debug_dir: Option<&Path>,
+) -> Result<Option<OcrResult>> {
+    let capture = capture_at_cursor()?;
+    // The area is in logical pixels, which are larger on HiDPI screens.
+    let scale = if opts.dpi_aware_crop {
+        capture.scale_factor.max(1.0)
+    } else {
+        1.0
+    };
+    let w = (w as f64 * scale) as u32;
+    let h = (h as f64 * scale) as u32;
+    let (iw, ih) = capture.image.dimensions();
+    let x1 = capture.x.saturating_sub(w / 2);
+    let y1 = capture.y.saturating_sub(h / 2);
+    let x2 = (capture.x + w / 2).min(iw);
+    let y2 = (capture.y + h / 2).min(ih);
+    if x2 <= x1 || y2 <= y1 {
+        return Err(anyhow!("empty area"));
+    }
+    let crop = capture.image.crop_imm(x1, y1, x2 - x1, y2 - y1);
+    let lines = match recognize_stages(engine, &crop, opts, cancelled)? {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let hit = hit_test(
+        &lines,
+        (capture.x - x1) as f32,
+        (capture.y - y1) as f32,
+        HIT_TOLERANCE * scale as f32,
+    );
+    if let Some(dir) = debug_dir {
+        if let Err(e) = save_capture(dir, &crop, &lines, hit.as_ref()) {
+            error!("fail to save OCR capture. {:?}", e);
+        }
+    }
+    Ok(Some(OcrResult {
+        hit,
+        ..OcrResult::new(lines)
+    }))
+}
+
+/// Recognizes all text of an image, grouped by lines. Word rects are in
+/// pixels of the given image whatever the preprocessing did.
+pub fn recognize_image(
+    engine: &OcrEngine,
+    img: &DynamicImage,
+    opts: Preprocess,
+) -> Result<Vec<OcrLine>> {
+    Ok(recognize_stages(engine, img, opts, &|| false)?.unwrap_or_default())
+}
+
+/// Same as `recognize_image`, checking `cancelled` before each stage.
+/// Returns `None` if it's cancelled.
+fn recognize_stages(
+    engine: &OcrEngine,
+    img: &DynamicImage,
+    opts: Preprocess,
+    cancelled: &dyn Fn() -> bool,
+) -> Result<Option<Vec<OcrLine>>> {
+    let (img, factor) = preprocess(img, opts);
+    let factor = factor as i32;
+    let img = img.to_rgb8();
+    let source = ImageSource::from_bytes(img.as_raw(), img.dimensions())?;
+    if cancelled() {
+        return Ok(None);
+    }
+    let input = engine.prepare_input(source)?;
+    let word_rects = engine.detect_words(&input)?;
+    if cancelled() {
+        return Ok(None);
+    }
+    let line_rects = engine.find_text_lines(&input, &word_rects);
+    let lines = engine.recognize_text(&input, &line_rects)?;
+    let lines = lines
+        .into_iter()
+        .flatten()
+        .map(|line| {
+            let words = line
+                .words()
+                .map(|wd| {
+                    let rect = wd.bounding_rect();
+                    OcrWord {
+                        text: wd.to_string(),
+                        x: rect.left() / factor,
+                        y: rect.top() / factor,
+                        width: rect.width() / factor,
+                        height: rect.height() / factor,
+                    }
+                })
+                .collect::<Vec<OcrWord>>();
+            OcrLine::new(words)
+        })
+        .filter(|x| !x.words.is_empty())
+        .collect();
+    Ok(Some(lines))
+}
+
+/// A capture of the monitor the mouse pointer is on
+pub struct CursorCapture {
+    pub image: DynamicImage,
+    /// The mouse pointer in pixels of the image
+    pub x: u32,
+    pub y: u32,
+    /// Scale factor of the monitor
+    pub scale_factor: f64,
+}
+
+/// Captures the whole monitor the mouse pointer is on.
+pub fn capture_at_cursor() -> Result<CursorCapture> {
+    let (x, y) = match Mouse::get_mouse_position() {
+        Mouse::Position { x, y } => (x, y),
+        Mouse::Error => return Err(anyhow!("fail to get mouse position")),
+    };
+    for monitor in xcap::Monitor::all()? {
+        let (mx, my) = (monitor.x()?, monitor.y()?);
+        let (mw, mh) = (monitor.width()? as i32, monitor.height()? as i32);
+        debug!("Screen: {} {}, {} {}", mx, my, mw, mh);
+        if x >= mx && x <= mx + mw && y >= my && y <= my + mh {
+            let image = DynamicImage::from(monitor.capture_image()?);
+            // The image may have more pixels than the monitor has points on
+            // HiDPI screens.
+            let scale = image.width() as f64 / mw.max(1) as f64;
+            let px = (((x - mx) as f64 * scale) as u32).min(image.width());
+            let py = (((y - my) as f64 * scale) as u32).min(image.height());
+            let scale_factor = monitor.scale_factor().map(|v| v as f64).unwrap_or(scale);
+            return Ok(CursorCapture {
+                image,
+                x: px,
+                y: py,
+                scale_factor,
+            });
+        }
+    }
+    Err(anyhow!("no monitor at {} {}", x, y))
+}
+
+/// Recognizes all text of an image file on the blocking pool.
+pub async fn recognize_file(ah: &AppHandle, file: &Path) -> Result<OcrResult> {
+    let engine = current_engine()?;
+    let opts = Preprocess::from_settings(ah).await;
+    let file = file.to_path_buf();
+    let lines = tokio::task::spawn_blocking(move || {
+        let img = image::open(&file)?;
+        recognize_image(&engine, &img, opts)
+    })
+    .await??;
+    Ok(OcrResult::new(lines))
+}
+
+/// Recognizes all text of the image on the clipboard on the blocking pool.
+pub async fn recognize_clipboard(ah: &AppHandle) -> Result<OcrResult> {
+    let engine = current_engine()?;
+    let opts = Preprocess::from_settings(ah).await;
+    let clip = ah
+        .clipboard()
+        .read_image()
+        .map_err(|e| anyhow!("no image on the clipboard. {}", e))?;
+    let img = RgbaImage::from_raw(clip.width(), clip.height(), clip.rgba().to_vec())
+        .ok_or_else(|| anyhow!("invalid clipboard image"))?;
+    let lines = tokio::task::spawn_blocking(move || {
+        recognize_image(&engine, &DynamicImage::from(img), opts)
+    })
+    .await??;
+    Ok(OcrResult::new(lines))
+}
@@ -0,0 +1,114 @@
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, GenericImageView};
+use log::error;
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, WebviewUrl, WebviewWindowBuilder};
+
+use super::{
+    debug::{debug_directory, save_capture},
+    engine::current_engine,
+    preprocess::Preprocess,
+    recognize::{capture_at_cursor, recognize_image},
+    OcrRegion, OcrResult, OCR_RESULT_EVENT,
+};
+use crate::lookup::send_to_main;
+
+pub static OVERLAY_WINDOW: &str = "ocr-overlay";
+static OVERLAY_ROUTE: &str = "ocr-overlay";
+
+/// Screenshot taken before the overlay is shown, so the overlay is not in it
+#[derive(Default)]
+pub struct RegionCapture(Mutex<Option<DynamicImage>>);
+
+/// Captures the monitor under the mouse pointer and covers it with a
+/// transparent window to select a region on.
+pub async fn open_overlay(ah: &AppHandle) -> Result<()> {
+    let img = tokio::task::spawn_blocking(capture_at_cursor).await??.image;
+    *ah.state::<RegionCapture>().0.lock().unwrap() = Some(img);
+
+    let cursor = ah.cursor_position()?;
+    let monitor = ah
+        .monitor_from_point(cursor.x, cursor.y)?
+        .or(ah.primary_monitor()?)
+        .ok_or_else(|| anyhow!("no monitor"))?;
+    if let Some(win) = ah.get_webview_window(OVERLAY_WINDOW) {
+        win.close()?;
+    }
+    let win = WebviewWindowBuilder::new(ah, OVERLAY_WINDOW, WebviewUrl::App(OVERLAY_ROUTE.into()))
+        .title("Beluga OCR")
+        .decorations(false)
+        .transparent(true)
+        .shadow(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(false)
+        .visible(false)
+        .build()?;
+    win.set_position(PhysicalPosition::new(
+        monitor.position().x,
+        monitor.position().y,
+    ))?;
+    win.set_size(PhysicalSize::new(
+        monitor.size().width,
+        monitor.size().height,
+    ))?;
+    win.show()?;
+    win.set_focus()?;
+    Ok(())
+}
+
+pub fn close_overlay(ah: &AppHandle) {
+    ah.state::<RegionCapture>().0.lock().unwrap().take();
+    if let Some(win) = ah.get_webview_window(OVERLAY_WINDOW) {
+        if let Err(e) = win.close() {
+            error!("fail to close OCR overlay. {}", e);
+        }
+    }
+}
+
+/// Recognizes the text in a region of the captured screen.
+pub async fn recognize_region(ah: &AppHandle, region: OcrRegion) -> Result<OcrResult> {
+    let img = ah
+        .state::<RegionCapture>()
+        .0
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| anyhow!("no screen captured"))?;
+    if region.view_width <= 0.0 || region.view_height <= 0.0 {
+        return Err(anyhow!("invalid view size"));
+    }
+    // The view covers the captured monitor, whose image may have more pixels
+    // than the view on HiDPI screens.
+    let (iw, ih) = img.dimensions();
+    let sx = iw as f64 / region.view_width;
+    let sy = ih as f64 / region.view_height;
+    let x = ((region.x * sx).max(0.0) as u32).min(iw);
+    let y = ((region.y * sy).max(0.0) as u32).min(ih);
+    let w = ((region.width * sx) as u32).min(iw - x);
+    let h = ((region.height * sy) as u32).min(ih - y);
+    if w == 0 || h == 0 {
+        return Err(anyhow!("empty region"));
+    }
+    let crop = img.crop_imm(x, y, w, h);
+    let engine = current_engine()?;
+    let opts = Preprocess::from_settings(ah).await;
+    let debug_dir = debug_directory(ah).await;
+    let lines = tokio::task::spawn_blocking(move || {
+        let lines = recognize_image(&engine, &crop, opts)?;
+        if let Some(dir) = debug_dir {
+            if let Err(e) = save_capture(&dir, &crop, &lines, None) {
+                error!("fail to save OCR capture. {:?}", e);
+            }
+        }
+        anyhow::Ok(lines)
+    })
+    .await??;
+    Ok(OcrResult::new(lines))
+}
+
+/// Sends a result to the main window, creating it if it's closed.
+pub fn deliver_result(ah: &AppHandle, result: &OcrResult) -> Result<()> {
+    send_to_main(ah, OCR_RESULT_EVENT, "__OCR_RESULT__", result)
+}
@@ -0,0 +1,94 @@
+use anyhow::{anyhow, Result};
+use log::error;
+use tauri::{
+    AppHandle, Emitter, Manager, PhysicalPosition, WebviewUrl, WebviewWindow, WebviewWindowBuilder,
+    WindowEvent,
+};
+
+use crate::lookup::{init_script, LookupRequest};
+
+pub static POPUP_WINDOW: &str = "popup";
+static POPUP_ROUTE: &str = "popup";
+/// Sent to the popup window with a `LookupRequest`
+static POPUP_LOOKUP_EVENT: &str = "popup_lookup";
+/// Logical size of the popup window
+static POPUP_WIDTH: f64 = 420.0;
+static POPUP_HEIGHT: f64 = 320.0;
+/// Distance from the mouse pointer in logical pixels
+static POPUP_OFFSET: f64 = 16.0;
+
+/// Looks up a word in a small popup window next to the mouse pointer. The
+/// window is hidden when it loses focus and reused for the next lookup.
+pub fn show_popup(ah: &AppHandle, req: LookupRequest) -> Result<()> {
+    let win = match ah.get_webview_window(POPUP_WINDOW) {
+        Some(v) => {
+            v.emit_to(POPUP_WINDOW, POPUP_LOOKUP_EVENT, req)?;
+            v
+        }
+        None => {
+            let script = init_script("__POPUP_LOOKUP__", &req)?;
+            let win =
+                WebviewWindowBuilder::new(ah, POPUP_WINDOW, WebviewUrl::App(POPUP_ROUTE.into()))
+                    .title("Beluga")
+                    .inner_size(POPUP_WIDTH, POPUP_HEIGHT)
+                    .decorations(false)
+                    .always_on_top(true)
+                    .skip_taskbar(true)
+                    .resizable(false)
+                    .visible(false)
+                    .initialization_script(&script)
+                    .build()?;
+            let handle = win.clone();
+            win.on_window_event(move |event| {
+                if let WindowEvent::Focused(false) = event {
+                    if let Err(e) = handle.hide() {
+                        error!("fail to hide popup. {}", e);
+                    }
+                }
+            });
+            win
+        }
+    };
+    place_near_cursor(ah, &win)?;
+    win.show()?;
+    win.set_focus()?;
+    Ok(())
+}
+
+/// Puts the window below and to the right of the mouse pointer, or on the
+/// other side where it would leave the monitor.
+fn place_near_cursor(ah: &AppHandle, win: &WebviewWindow) -> Result<()> {
+    let cursor = ah.cursor_position()?;
+    let monitor = ah
+        .monitor_from_point(cursor.x, cursor.y)?
+        .or(ah.primary_monitor()?)
+        .ok_or_else(|| anyhow!("no monitor"))?;
+    let scale = monitor.scale_factor();
+    let (w, h) = (POPUP_WIDTH * scale, POPUP_HEIGHT * scale);
+    let offset = POPUP_OFFSET * scale;
+    let left = monitor.position().x as f64;
+    let top = monitor.position().y as f64;
+    let right = left + monitor.size().width as f64;
+    let bottom = top + monitor.size().height as f64;
+    let mut x = cursor.x + offset;
+    if x + w > right {
+        x = cursor.x - offset - w;
+    }
+    let mut y = cursor.y + offset;
+    if y + h > bottom {
+        y = cursor.y - offset - h;
+    }
+    win.set_position(PhysicalPosition::new(
+        x.clamp(left, (right - w).max(left)) as i32,
+        y.clamp(top, (bottom - h).max(top)) as i32,
+    ))?;
+    Ok(())
+}
+
+pub fn hide_popup(ah: &AppHandle) {
+    if let Some(win) = ah.get_webview_window(POPUP_WINDOW) {
+        if let Err(e) = win.hide() {
+            error!("fail to hide popup. {}", e);
+        }
+    }
+}
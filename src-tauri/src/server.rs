@@ -1,112 +1,328 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
 
 use axum::{
     extract::{Query, State},
     http::{
         header::{self, SET_COOKIE},
-        StatusCode, Uri,
+        HeaderMap, HeaderValue, StatusCode, Uri,
     },
-    response::{AppendHeaders, Html, IntoResponse},
+    response::{AppendHeaders, Html, IntoResponse, Response},
     routing::get,
-    Router,
+    Json, Router,
 };
 use axum_extra::extract::CookieJar;
 use beluga_core::dictionary::{Dictionary, NodeCache};
 use log::warn;
-use serde::Deserialize;
-use tauri::AppHandle;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
 use tokio::{
-    fs,
     net::TcpListener,
     sync::{Mutex, RwLock},
 };
 
-use crate::{base::get_resource_directory, settings::Settings};
+use crate::{
+    asset_cache::{self, AssetCache, AssetCacheStats},
+    entry, html,
+    settings::Settings,
+    theme,
+    utils::random_token,
+};
 
 static DICT_COOKIE_NAME: &str = "dict_id";
 static DICT_JS_FILE: &str = "entry.js";
+pub static SERVER_PORT_FILE: &str = "server.json";
+pub static ENTRY_VIEWED_EVENT: &str = "entry_viewed";
+/// Sent with the `ServerStatus` once the server is listening or fails to
+pub static SERVER_STATUS_EVENT: &str = "server_status";
+/// Ports tried in order when no port is configured
+static SERVER_PORTS: std::ops::RangeInclusive<u16> = 19000..=19099;
+/// Put between the homographs of a headword
+pub static ENTRY_SEPARATOR: &str = "<hr class=\"beluga-entry-separator\" />";
+static ASSET_CACHE_SIZE: usize = 32 * 1024 * 1024;
+static ASSET_CACHE_ITEM_SIZE: usize = 5 * 1024 * 1024;
+
+static STATUS: std::sync::RwLock<Option<ServerStatus>> = std::sync::RwLock::new(None);
+
+/// The port the server listens on, or why it can't listen
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServerStatus {
+    pub port: u16,
+    pub error: Option<String>,
+}
+
+pub fn server_status() -> ServerStatus {
+    STATUS.read().unwrap().clone().unwrap_or_default()
+}
+
+fn set_server_status(ah: Option<&AppHandle>, status: ServerStatus) {
+    *STATUS.write().unwrap() = Some(status.clone());
+    if let Some(ah) = ah {
+        if let Err(e) = ah.emit(SERVER_STATUS_EVENT, status) {
+            warn!("fail to notify {}. {}", SERVER_STATUS_EVENT, e);
+        }
+    }
+}
 
 #[derive(Clone)]
 struct AppState {
     pub dicts: Arc<RwLock<HashMap<u32, Arc<Mutex<Dictionary>>>>>,
     pub cache: Arc<RwLock<NodeCache>>,
     pub settings: Arc<RwLock<Settings>>,
-    pub ah: AppHandle,
-    pub entry_js_cache: Arc<RwLock<String>>,
-    pub static_file_cache: Arc<RwLock<HashMap<PathBuf, Vec<u8>>>>,
+    pub resource_dir: PathBuf,
+    pub asset_cache: Arc<Mutex<AssetCache>>,
+    pub ah: Option<AppHandle>,
+    /// Written to the port file and required by `/@entries` and `/@stats`
+    /// as `Authorization: Bearer {token}`, so only programs that can read
+    /// the file use them. The webview doesn't call these routes.
+    pub token: String,
 }
 
-pub async fn start_server(
+/// Listens on `port`, or on the first free port from 19000 if it's None.
+/// A failure is also reported as the server status.
+pub async fn bind_server(ah: Option<&AppHandle>, port: Option<u16>) -> Result<TcpListener> {
+    let ports = match port {
+        Some(v) => v..=v,
+        None => SERVER_PORTS.clone(),
+    };
+    let mut bind_error = None;
+    for port in ports {
+        let addr = format!("127.0.0.1:{}", port);
+        match TcpListener::bind(addr).await {
+            Ok(v) => return Ok(v),
+            Err(e) => bind_error = Some(e),
+        }
+    }
+    let msg = match port {
+        Some(port) => {
+            let e = bind_error.map(|x| x.to_string()).unwrap_or_default();
+            format!("port {} is not available. {}", port, e)
+        }
+        None => format!(
+            "no available port from {} to {}",
+            SERVER_PORTS.start(),
+            SERVER_PORTS.end()
+        ),
+    };
+    Err(server_error(ah, msg))
+}
+
+/// Serves entries on a listener from `bind_server`. The port and the token
+/// of the server are written to `port_file` while it's running, for other
+/// programs to find it.
+#[allow(clippy::too_many_arguments)]
+pub async fn start_server<F>(
     settings: Arc<RwLock<Settings>>,
     dicts: Arc<RwLock<HashMap<u32, Arc<Mutex<Dictionary>>>>>,
     cache: Arc<RwLock<NodeCache>>,
-    ah: AppHandle,
-) {
+    resource_dir: PathBuf,
+    ah: Option<AppHandle>,
+    listener: TcpListener,
+    port_file: Option<PathBuf>,
+    shutdown: F,
+) -> Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let port = listener.local_addr()?.port();
     let settings2 = settings.clone();
+    let ah2 = ah.clone();
+    let token = random_token();
     let state = AppState {
         settings,
         dicts,
         cache,
+        resource_dir,
+        asset_cache: Arc::new(Mutex::new(AssetCache::new(
+            ASSET_CACHE_SIZE,
+            ASSET_CACHE_ITEM_SIZE,
+        ))),
         ah,
-        entry_js_cache: Arc::new(RwLock::new("".to_string())),
-        static_file_cache: Arc::new(RwLock::new(HashMap::new())),
+        token: token.clone(),
     };
     let app = Router::new()
         .route("/@entry", get(get_entry))
+        .route("/@entries", get(get_entries))
         .route("/@resource", get(get_resource))
+        .route("/@stats", get(get_stats))
         .fallback(get(get_static_file))
         .with_state(state);
 
-    let mut port: u32 = 19000;
-    for _ in 0..100 {
-        let addr = format!("0.0.0.0:{}", port);
-        if let Ok(v) = TcpListener::bind(addr).await {
-            let mut settings_lock = settings2.write().await;
-            settings_lock.server_port = port;
-            drop(settings_lock);
-            axum::serve(v, app).await.expect("fail to start server");
-            break;
+    settings2.write().await.server_port = port;
+    set_server_status(ah2.as_ref(), ServerStatus { port, error: None });
+    if let Some(file) = &port_file {
+        if let Err(e) = write_port_file(file, port, &token) {
+            warn!("fail to write port file {}. {:?}", file.display(), e);
+        }
+    }
+    let r = axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown)
+        .await;
+    if let Some(file) = &port_file {
+        remove_port_file(file);
+    }
+    r?;
+    Ok(())
+}
+
+fn server_error(ah: Option<&AppHandle>, msg: String) -> anyhow::Error {
+    set_server_status(
+        ah,
+        ServerStatus {
+            port: 0,
+            error: Some(msg.clone()),
+        },
+    );
+    anyhow!("fail to start server. {}", msg)
+}
+
+/// Written while the server is running and removed when the app exits. A
+/// file left by a crash has the `pid` of a process that is gone, and the
+/// server on its port, if any, rejects its token.
+#[derive(Serialize, Deserialize)]
+pub struct PortFile {
+    pub port: u16,
+    pub pid: u32,
+    #[serde(default)]
+    pub token: String,
+}
+
+pub fn write_port_file(file: &Path, port: u16, token: &str) -> Result<()> {
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let content = serde_json::to_string(&PortFile {
+        port,
+        pid: std::process::id(),
+        token: token.to_string(),
+    })?;
+    std::fs::write(file, content)?;
+    Ok(())
+}
+
+/// Removes the port file unless another process has taken it over.
+pub fn remove_port_file(file: &Path) {
+    if let Ok(s) = std::fs::read_to_string(file) {
+        if let Ok(v) = serde_json::from_str::<PortFile>(&s) {
+            if v.pid != std::process::id() {
+                return;
+            }
         }
-        port += 1;
     }
-    panic!("fail to start server due to no available port");
+    let _ = std::fs::remove_file(file);
 }
 
 #[derive(Deserialize)]
 struct EntryQuery {
     dict_id: u32,
     name: String,
+    format: Option<String>,
+    dark: Option<bool>,
+    scale: Option<u32>,
+    /// How the entry is reached, `link` for entry:// links and `history` for
+    /// going back or forward
+    nav: Option<String>,
+    /// The window showing the entry, `popup` for the popup window
+    view: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EntryViewed {
+    pub dict_id: u32,
+    /// Headword of the entry shown, which may differ from the query after
+    /// redirects
+    pub name: String,
+    pub query: String,
+    pub nav: Option<String>,
+    pub view: Option<String>,
 }
 
 async fn get_entry(State(state): State<AppState>, params: Query<EntryQuery>) -> impl IntoResponse {
     let dicts_lock = state.dicts.read().await;
     if let Some(dict) = dicts_lock.get(&params.dict_id) {
         let mut dict_lock = dict.lock().await;
-        if let Some(content) = dict_lock
-            .search_entry(state.cache.clone(), &params.name)
-            .await
-        {
-            let js_cache = state.entry_js_cache.read().await;
-            let js = if js_cache.is_empty() {
-                let static_dir = get_resource_directory(state.ah.clone());
-                let jsfile = static_dir.join(DICT_JS_FILE);
-                if let Ok(v) = fs::read_to_string(jsfile).await {
-                    v
-                } else {
+        let entries =
+            entry::search_entries(&mut dict_lock, state.cache.clone(), &params.name).await;
+        if !entries.is_empty() {
+            if params.format.as_deref() == Some("text") {
+                let text = entries
+                    .iter()
+                    .map(|x| html::to_text(&x.content))
+                    .collect::<Vec<String>>()
+                    .join("\n\n");
+                return ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], text)
+                    .into_response();
+            }
+            let headword = entries[0].name.clone();
+            if let Some(ah) = &state.ah {
+                let _ = ah.emit(
+                    ENTRY_VIEWED_EVENT,
+                    EntryViewed {
+                        dict_id: params.dict_id,
+                        name: headword.clone(),
+                        query: params.name.clone(),
+                        nav: params.nav.clone(),
+                        view: params.view.clone(),
+                    },
+                );
+            }
+            let content = entries
+                .into_iter()
+                .map(|x| x.content)
+                .collect::<Vec<String>>()
+                .join(ENTRY_SEPARATOR);
+            let jsfile = state.resource_dir.join(DICT_JS_FILE);
+            let js = match asset_cache::read_file(&state.asset_cache, &jsfile).await {
+                Ok(v) => String::from_utf8_lossy(&v).to_string(),
+                Err(e) => {
+                    warn!("fail to read {}. {}", jsfile.display(), e);
                     "".to_string()
                 }
-            } else {
-                js_cache.to_string()
             };
             let settings_lock = state.settings.read().await;
             let dev_mode = settings_lock.config.dev_mode;
+            let sanitize = need_sanitize(&settings_lock, params.dict_id);
+            let dict_name = settings_lock
+                .config
+                .dicts
+                .iter()
+                .find(|x| x.id == params.dict_id)
+                .map(|x| x.name.clone());
+            let theme_css = theme::theme_css(
+                params.dark.unwrap_or(settings_lock.config.dark_mode),
+                params.scale.unwrap_or(settings_lock.config.font_scale),
+            );
+            let config_dir = settings_lock.config_dir();
             drop(settings_lock);
+            let mut user_css = String::new();
+            for file in theme::user_css_files(&config_dir, dict_name.as_deref()) {
+                if let Ok(v) = asset_cache::read_file(&state.asset_cache, &file).await {
+                    user_css.push_str(&String::from_utf8_lossy(&v));
+                    user_css.push('\n');
+                }
+            }
             let (dict_css, dict_js) = if let Ok(v) = dict_lock.get_css_js(dev_mode).await {
                 v
             } else {
                 warn!("fail to get dict css and js");
                 ("".to_string(), "".to_string())
             };
+            let (content, dict_css, dict_js) = if sanitize {
+                (
+                    html::sanitize(&content),
+                    html::sanitize_css(&dict_css),
+                    "".to_string(),
+                )
+            } else {
+                (content, dict_css, dict_js)
+            };
+            let nonce = random_token();
             let html = format!(
                 "
 <!DOCTYPE html>
@@ -114,24 +330,40 @@ async fn get_entry(State(state): State<AppState>, params: Query<EntryQuery>) ->
     <head>
         <meta charset=\"UTF-8\" />
         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\" />
-        <title></title>
+        <meta name=\"beluga-headword\" content=\"{}\" />
+        <title>{}</title>
+        <style>{}</style>
         <style>{}</style>
-        <script>{}</script>
-        <script>{}</script>
+        <style>{}</style>
+        <script nonce=\"{}\">{}</script>
+        <script nonce=\"{}\">{}</script>
     </head>
     <body>{}</body>
 </html>
             ",
-                dict_css, dict_js, js, content
+                html::escape(&headword),
+                html::escape(&headword),
+                dict_css,
+                user_css,
+                theme_css,
+                nonce,
+                dict_js,
+                nonce,
+                js,
+                content
             );
-            (
+            let mut response = (
                 AppendHeaders([(
                     SET_COOKIE,
                     format!("{}={}", DICT_COOKIE_NAME, params.dict_id),
                 )]),
                 Html(html),
             )
-                .into_response()
+                .into_response();
+            if sanitize {
+                set_content_security_policy(&mut response, Some(&nonce));
+            }
+            response
         } else {
             StatusCode::NOT_FOUND.into_response()
         }
@@ -140,9 +372,36 @@ async fn get_entry(State(state): State<AppState>, params: Query<EntryQuery>) ->
     }
 }
 
+#[derive(Deserialize)]
+struct EntriesQuery {
+    dict_id: u32,
+    name: String,
+}
+
+async fn get_entries(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    params: Query<EntriesQuery>,
+) -> impl IntoResponse {
+    if !authorized(&state, &headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let dicts_lock = state.dicts.read().await;
+    if let Some(dict) = dicts_lock.get(&params.dict_id) {
+        let mut dict_lock = dict.lock().await;
+        let entries =
+            entry::search_entries(&mut dict_lock, state.cache.clone(), &params.name).await;
+        Json(entries).into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
 #[derive(Deserialize)]
 struct ResourceQuery {
     name: String,
+    /// Defaults to the dictionary of the last entry shown
+    dict_id: Option<u32>,
 }
 
 async fn get_resource(
@@ -150,7 +409,9 @@ async fn get_resource(
     params: Query<ResourceQuery>,
     jar: CookieJar,
 ) -> impl IntoResponse {
-    let dict_id = if let Some(v) = jar.get(DICT_COOKIE_NAME) {
+    let dict_id = if let Some(v) = params.dict_id {
+        v
+    } else if let Some(v) = jar.get(DICT_COOKIE_NAME) {
         if let Ok(v) = v.value().parse::<u32>() {
             v
         } else {
@@ -190,31 +451,83 @@ async fn get_static_file(
     } else {
         return (StatusCode::BAD_REQUEST, "No dict id in cookie").into_response();
     };
+    // Only plain relative paths inside the dictionary directory are served.
+    let path = Path::new(uri.path().trim_start_matches('/'));
+    if path
+        .components()
+        .any(|x| !matches!(x, Component::Normal(_)))
+    {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
     let settings_lock = state.settings.read().await;
-    let dev_mode = settings_lock.config.dev_mode;
-    let dicts_dir = std::path::Path::new(&settings_lock.config.dict_dir);
-    for item in &settings_lock.config.dicts {
-        if item.id == dict_id {
-            let file = dicts_dir.join(&item.name).join(uri.path());
-            let tp = mime_guess::from_path(uri.path()).first_or_octet_stream();
-            let mut static_file_cache = state.static_file_cache.write().await;
-            if let Some(v) = static_file_cache.get(&file) {
-                if dev_mode {
-                    static_file_cache.clear();
-                } else {
-                    return ([(header::CONTENT_TYPE, tp.to_string())], v.clone()).into_response();
-                }
-            }
-            if file.is_file() {
-                if let Ok(content) = fs::read(&file).await {
-                    if !dev_mode && content.len() <= 5 * 1024 * 1024 {
-                        static_file_cache.insert(file, content.clone());
-                    }
-                    return ([(header::CONTENT_TYPE, tp.to_string())], content).into_response();
-                }
-            }
-            break;
-        }
+    let sanitize = need_sanitize(&settings_lock, dict_id);
+    let dicts_dir = Path::new(&settings_lock.config.dict_dir);
+    let file = match settings_lock.config.dicts.iter().find(|x| x.id == dict_id) {
+        Some(item) => dicts_dir.join(&item.name).join(path),
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    drop(settings_lock);
+    let content = match asset_cache::read_file(&state.asset_cache, &file).await {
+        Ok(v) => v,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let tp = mime_guess::from_path(&file).first_or_octet_stream();
+    let mut response = ([(header::CONTENT_TYPE, tp.to_string())], content).into_response();
+    if sanitize {
+        set_content_security_policy(&mut response, None);
+    }
+    response
+}
+
+#[derive(Serialize)]
+struct Stats {
+    asset_cache: AssetCacheStats,
+}
+
+async fn get_stats(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if !authorized(&state, &headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let asset_cache = state.asset_cache.lock().await.stats();
+    Json(Stats { asset_cache }).into_response()
+}
+
+fn authorized(state: &AppState, headers: &HeaderMap) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))
+        .is_some_and(|x| x == state.token)
+}
+
+/// Whether entries of a dictionary are sanitized, i.e. sanitizer mode is on
+/// and the dictionary is not trusted.
+fn need_sanitize(settings: &Settings, dict_id: u32) -> bool {
+    settings.config.sanitize_entries
+        && !settings
+            .config
+            .dicts
+            .iter()
+            .any(|x| x.id == dict_id && x.trusted)
+}
+
+/// Only scripts with the nonce (the injected entry.js) may run, and nothing
+/// may be loaded from or sent to other origins.
+fn set_content_security_policy(response: &mut Response, nonce: Option<&str>) {
+    let script_src = match nonce {
+        Some(v) => format!("'nonce-{}'", v),
+        None => "'none'".to_string(),
+    };
+    let csp = format!(
+        "default-src 'self' data:; script-src {}; style-src 'self' 'unsafe-inline'; \
+         img-src 'self' data:; media-src 'self' data:; font-src 'self' data:; \
+         connect-src 'none'; object-src 'none'; frame-src 'none'; base-uri 'none'; \
+         form-action 'none'",
+        script_src
+    );
+    if let Ok(v) = HeaderValue::from_str(&csp) {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_SECURITY_POLICY, v);
     }
-    StatusCode::NOT_FOUND.into_response()
 }
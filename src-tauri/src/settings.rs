@@ -1,10 +1,18 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use log::error;
-use std::{fs, path::Path};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
 use tauri::{AppHandle, Emitter};
 
 use serde::{Deserialize, Serialize};
 
+use crate::dict_server::DEFAULT_DICT_SERVER_PORT;
+
+/// Sent with the `Configuration` when it's changed
+pub static SETTINGS_CHANGED_EVENT: &str = "settings_changed";
 static SETTINGS_FILE: &str = "settings.json";
 static DICTS_DIR: &str = "dicts";
 
@@ -16,6 +24,10 @@ fn default_dicts() -> Vec<DictItem> {
     vec![]
 }
 
+fn default_dict_groups() -> Vec<DictGroup> {
+    vec![]
+}
+
 fn default_cache_size() -> u32 {
     100
 }
@@ -28,8 +40,38 @@ fn default_win_height() -> u32 {
     650
 }
 
-fn default_ocr_shortcut() -> String {
-    "Alt+X".to_string()
+/// Actions of global shortcuts
+pub static SHORTCUT_OCR: &str = "ocr";
+pub static SHORTCUT_CLIPBOARD: &str = "clipboard";
+pub static SHORTCUT_TOGGLE_WINDOW: &str = "toggle_window";
+pub static SHORTCUT_QUICK_SEARCH: &str = "quick_search";
+pub static SHORTCUT_ACTIONS: [&str; 4] = [
+    SHORTCUT_OCR,
+    SHORTCUT_CLIPBOARD,
+    SHORTCUT_TOGGLE_WINDOW,
+    SHORTCUT_QUICK_SEARCH,
+];
+
+fn default_shortcuts() -> BTreeMap<String, String> {
+    BTreeMap::from([
+        (SHORTCUT_OCR.to_string(), "Alt+X".to_string()),
+        (SHORTCUT_CLIPBOARD.to_string(), "".to_string()),
+        (SHORTCUT_TOGGLE_WINDOW.to_string(), "".to_string()),
+        (SHORTCUT_QUICK_SEARCH.to_string(), "".to_string()),
+    ])
+}
+
+fn default_ocr_shortcut_enabled() -> bool {
+    true
+}
+
+/// OCR the word under the mouse pointer
+pub static OCR_MODE_CURSOR: &str = "cursor";
+/// OCR all text in a region selected on screen
+pub static OCR_MODE_REGION: &str = "region";
+
+fn default_ocr_mode() -> String {
+    OCR_MODE_CURSOR.to_string()
 }
 
 fn default_ocr_width() -> u32 {
@@ -40,6 +82,39 @@ fn default_ocr_height() -> u32 {
     50
 }
 
+fn default_ocr_model() -> String {
+    "".to_string()
+}
+
+fn default_ocr_preprocess() -> bool {
+    true
+}
+
+fn default_ocr_debug() -> bool {
+    false
+}
+
+fn default_clipboard_watch() -> bool {
+    false
+}
+
+fn default_clipboard_max_length() -> u32 {
+    50
+}
+
+fn default_clipboard_scripts() -> Vec<String> {
+    vec![]
+}
+
+/// Scripts copied text can be filtered by
+pub static CLIPBOARD_SCRIPTS: [&str; 9] = [
+    "latin", "greek", "cyrillic", "hebrew", "arabic", "thai", "hangul", "kana", "han",
+];
+
+fn default_popup_lookup() -> bool {
+    false
+}
+
 fn default_prefix_limit() -> u32 {
     5
 }
@@ -52,11 +127,46 @@ fn default_dev_mode() -> bool {
     false
 }
 
+fn default_dark_mode() -> bool {
+    false
+}
+
+fn default_font_scale() -> u32 {
+    100
+}
+
+fn default_sanitize_entries() -> bool {
+    false
+}
+
+fn default_http_port() -> u16 {
+    0
+}
+
+fn default_dict_server() -> bool {
+    false
+}
+
+fn default_dict_server_port() -> u16 {
+    DEFAULT_DICT_SERVER_PORT
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DictItem {
     pub id: u32,
     pub name: String,
     pub available: bool,
+    /// Scripts of trusted dictionaries are kept in sanitizer mode.
+    #[serde(default)]
+    pub trusted: bool,
+}
+
+/// A named set of dictionaries. Switching to a group makes only its
+/// dictionaries available.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DictGroup {
+    pub name: String,
+    pub dicts: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -65,30 +175,136 @@ pub struct Configuration {
     pub dict_dir: String,
     #[serde(default = "default_dicts")]
     pub dicts: Vec<DictItem>,
+    #[serde(default = "default_dict_groups")]
+    pub dict_groups: Vec<DictGroup>,
     #[serde(default = "default_cache_size")]
     pub cache_size: u32,
     #[serde(default = "default_win_width")]
     pub win_width: u32,
     #[serde(default = "default_win_height")]
     pub win_height: u32,
-    #[serde(default = "default_ocr_shortcut")]
-    pub ocr_shortcut: String,
+    /// Global shortcuts by action, empty ones are disabled
+    #[serde(default = "default_shortcuts")]
+    pub shortcuts: BTreeMap<String, String>,
+    /// The OCR shortcut can be turned off without forgetting it
+    #[serde(default = "default_ocr_shortcut_enabled")]
+    pub ocr_shortcut_enabled: bool,
+    /// Replaced by `shortcuts`, only read to migrate old settings
+    #[serde(default, skip_serializing)]
+    ocr_shortcut: Option<String>,
+    #[serde(default = "default_ocr_mode")]
+    pub ocr_mode: String,
     #[serde(default = "default_ocr_width")]
     pub ocr_width: u32,
     #[serde(default = "default_ocr_height")]
     pub ocr_height: u32,
+    /// Text detection model file, the bundled one if empty
+    #[serde(default = "default_ocr_model")]
+    pub ocr_detection_model: String,
+    /// Text recognition model file, the bundled one if empty. Models of other
+    /// languages can be used here.
+    #[serde(default = "default_ocr_model")]
+    pub ocr_recognition_model: String,
+    #[serde(default = "default_ocr_preprocess")]
+    pub ocr_grayscale: bool,
+    /// Inverts light text on a dark background before OCR
+    #[serde(default = "default_ocr_preprocess")]
+    pub ocr_auto_invert: bool,
+    /// Enlarges small images before OCR
+    #[serde(default = "default_ocr_preprocess")]
+    pub ocr_upscale: bool,
+    #[serde(default = "default_ocr_preprocess")]
+    pub ocr_normalize_contrast: bool,
+    /// Scales the OCR area by the monitor scale factor
+    #[serde(default = "default_ocr_preprocess")]
+    pub ocr_dpi_aware_crop: bool,
+    /// Saves OCR captures with the rects of recognized words to the log
+    /// folder
+    #[serde(default = "default_ocr_debug")]
+    pub ocr_debug: bool,
+    /// Looks up text as it's copied
+    #[serde(default = "default_clipboard_watch")]
+    pub clipboard_watch: bool,
+    /// Longer copied text is not looked up, 0 for no limit
+    #[serde(default = "default_clipboard_max_length")]
+    pub clipboard_max_length: u32,
+    /// Copied text is looked up if its letters are in these scripts, any
+    /// script if empty
+    #[serde(default = "default_clipboard_scripts")]
+    pub clipboard_scripts: Vec<String>,
+    /// Shows words looked up by OCR and the clipboard in a popup window
+    /// next to the mouse pointer instead of the main window
+    #[serde(default = "default_popup_lookup")]
+    pub popup_lookup: bool,
     #[serde(default = "default_prefix_limit")]
     pub prefix_limit: u32,
     #[serde(default = "default_phrase_limit")]
     pub phrase_limit: u32,
     #[serde(default = "default_dev_mode")]
     pub dev_mode: bool,
+    #[serde(default = "default_dark_mode")]
+    pub dark_mode: bool,
+    /// Font size of entries in percent
+    #[serde(default = "default_font_scale")]
+    pub font_scale: u32,
+    #[serde(default = "default_sanitize_entries")]
+    pub sanitize_entries: bool,
+    /// Port of the HTTP server of entries, 0 for the first available one
+    /// from 19000
+    #[serde(default = "default_http_port")]
+    pub http_port: u16,
+    #[serde(default = "default_dict_server")]
+    pub dict_server: bool,
+    #[serde(default = "default_dict_server_port")]
+    pub dict_server_port: u16,
+}
+
+impl Configuration {
+    /// Shortcuts to register, without the turned off ones
+    pub fn active_shortcuts(&self) -> BTreeMap<String, String> {
+        let mut map = self.shortcuts.clone();
+        if !self.ocr_shortcut_enabled {
+            map.remove(SHORTCUT_OCR);
+        }
+        map
+    }
+
+    /// The group whose dictionaries are exactly the available ones
+    pub fn current_dict_group(&self) -> Option<&DictGroup> {
+        self.dict_groups.iter().find(|g| {
+            self.dicts
+                .iter()
+                .all(|x| x.available == g.dicts.contains(&x.name))
+        })
+    }
+
+    /// Makes only the dictionaries of a group available.
+    pub fn switch_dict_group(&mut self, name: &str) -> Result<()> {
+        let group = self
+            .dict_groups
+            .iter()
+            .find(|x| x.name == name)
+            .ok_or_else(|| anyhow!("no dictionary group {}", name))?;
+        for item in self.dicts.iter_mut() {
+            item.available = group.dicts.contains(&item.name);
+        }
+        Ok(())
+    }
+
+    fn migrate(&mut self) {
+        if let Some(v) = self.ocr_shortcut.take() {
+            self.shortcuts.insert(SHORTCUT_OCR.to_string(), v);
+        }
+        for (action, shortcut) in default_shortcuts() {
+            self.shortcuts.entry(action).or_insert(shortcut);
+        }
+    }
 }
 
 pub struct Settings {
     file: String,
     pub config: Configuration,
-    pub server_port: u32,
+    pub server_port: u16,
 }
 
 impl Settings {
@@ -101,7 +317,8 @@ impl Settings {
         let config_path = config_file.to_str().unwrap().to_string();
         if config_file.is_file() {
             let s = fs::read_to_string(&config_file)?;
-            if let Ok(v) = serde_json::from_str::<Configuration>(&s) {
+            if let Ok(mut v) = serde_json::from_str::<Configuration>(&s) {
+                v.migrate();
                 return Ok(Settings {
                     file: config_path,
                     config: v,
@@ -121,9 +338,16 @@ impl Settings {
         })
     }
 
+    pub fn config_dir(&self) -> PathBuf {
+        Path::new(&self.file)
+            .parent()
+            .map(|x| x.to_path_buf())
+            .unwrap_or_default()
+    }
+
     pub fn notify_changed(&self, ah: AppHandle) {
-        if let Err(e) = ah.emit("settings_changed", self.config.clone()) {
-            error!("fail to notify settings_changed. {}", e);
+        if let Err(e) = ah.emit(SETTINGS_CHANGED_EVENT, self.config.clone()) {
+            error!("fail to notify {}. {}", SETTINGS_CHANGED_EVENT, e);
         }
     }
 
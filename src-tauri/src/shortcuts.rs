@@ -0,0 +1,167 @@
+use std::sync::Mutex;
+
+use serde::Serialize;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+use {
+    crate::{
+        base::AppState,
+        clipboard::lookup_clipboard,
+        lookup::{send_to_main, toggle_main_window, LookupRequest},
+        ocr::{
+            recognize::ocr_at_cursor,
+            region::{deliver_result, open_overlay},
+        },
+        popup::show_popup,
+        settings::{
+            OCR_MODE_REGION, SHORTCUT_ACTIONS, SHORTCUT_CLIPBOARD, SHORTCUT_OCR,
+            SHORTCUT_QUICK_SEARCH, SHORTCUT_TOGGLE_WINDOW,
+        },
+    },
+    anyhow::{anyhow, Result},
+    log::{debug, error},
+    std::{collections::BTreeMap, str::FromStr},
+    tauri::{AppHandle, Emitter, Manager},
+    tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState},
+};
+
+/// Sent with the list of `ShortcutStatus` when shortcuts are registered
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub static SHORTCUT_STATUS_EVENT: &str = "shortcut_status";
+/// Sent to the main window to focus the search box
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+static QUICK_SEARCH_EVENT: &str = "quick_search";
+
+static STATUS: Mutex<Vec<ShortcutStatus>> = Mutex::new(Vec::new());
+
+/// A registered shortcut, with the error if it can't be registered, e.g.
+/// when another app has taken it
+#[derive(Debug, Clone, Serialize)]
+pub struct ShortcutStatus {
+    pub action: String,
+    pub shortcut: String,
+    pub error: Option<String>,
+}
+
+pub fn shortcut_status() -> Vec<ShortcutStatus> {
+    STATUS.lock().unwrap().clone()
+}
+
+/// Checks actions and shortcuts, and that no shortcut is used twice. Empty
+/// shortcuts are disabled.
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub fn validate_shortcuts(map: &BTreeMap<String, String>) -> Result<()> {
+    let mut seen: Vec<(Shortcut, &str)> = vec![];
+    for (action, value) in map {
+        if !SHORTCUT_ACTIONS.contains(&action.as_str()) {
+            return Err(anyhow!("invalid shortcut action: {}", action));
+        }
+        if value.is_empty() {
+            continue;
+        }
+        let sc = Shortcut::from_str(value)
+            .map_err(|e| anyhow!("invalid shortcut {} of {}. {}", value, action, e))?;
+        if let Some((_, other)) = seen.iter().find(|(x, _)| *x == sc) {
+            return Err(anyhow!(
+                "shortcut {} is used by both {} and {}",
+                value,
+                other,
+                action
+            ));
+        }
+        seen.push((sc, action));
+    }
+    Ok(())
+}
+
+/// Replaces all registered shortcuts and notifies the UI of the result.
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub fn register_shortcuts(ah: &AppHandle, map: &BTreeMap<String, String>) {
+    let gs = ah.global_shortcut();
+    if let Err(e) = gs.unregister_all() {
+        error!("fail to unregister shortcuts. {}", e);
+    }
+    let list = map
+        .iter()
+        .filter(|(_, v)| !v.is_empty())
+        .map(|(action, value)| {
+            let error = match Shortcut::from_str(value) {
+                Ok(sc) => gs.register(sc).err().map(|e| e.to_string()),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(e) = &error {
+                error!("fail to register {} shortcut {}. {}", action, value, e);
+            }
+            ShortcutStatus {
+                action: action.clone(),
+                shortcut: value.clone(),
+                error,
+            }
+        })
+        .collect::<Vec<ShortcutStatus>>();
+    *STATUS.lock().unwrap() = list.clone();
+    if let Err(e) = ah.emit(SHORTCUT_STATUS_EVENT, list) {
+        error!("fail to notify {}. {}", SHORTCUT_STATUS_EVENT, e);
+    }
+}
+
+/// Runs the action of a pressed shortcut.
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub async fn handle_shortcut(ah: &AppHandle, sc: Shortcut, state: ShortcutState) {
+    if !matches!(state, ShortcutState::Pressed) {
+        return;
+    }
+    let (action, ocr_mode, popup_lookup) = {
+        let state = ah.state::<AppState>();
+        let settings_lock = state.settings.read().await;
+        let config = &settings_lock.config;
+        let action = config
+            .shortcuts
+            .iter()
+            .find(|(_, v)| Shortcut::from_str(v).is_ok_and(|x| x == sc))
+            .map(|(k, _)| k.clone());
+        (action, config.ocr_mode.clone(), config.popup_lookup)
+    };
+    let action = match action {
+        Some(v) => v,
+        None => return,
+    };
+    debug!("shortcut action: {}", action);
+    let r = if action == SHORTCUT_OCR && ocr_mode == OCR_MODE_REGION {
+        open_overlay(ah).await
+    } else if action == SHORTCUT_OCR {
+        match ocr_at_cursor(ah).await {
+            Ok(Some(result)) => {
+                debug!(
+                    "recognized word: {:?}",
+                    result.hit.as_ref().map(|x| &x.text)
+                );
+                match result.hit.as_ref() {
+                    Some(hit) if popup_lookup && !hit.text.is_empty() => show_popup(
+                        ah,
+                        LookupRequest {
+                            text: hit.text.clone(),
+                            dict: None,
+                        },
+                    ),
+                    _ => deliver_result(ah, &result),
+                }
+            }
+            Ok(None) => {
+                debug!("cursor OCR is cancelled");
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    } else if action == SHORTCUT_CLIPBOARD {
+        lookup_clipboard(ah).await
+    } else if action == SHORTCUT_TOGGLE_WINDOW {
+        toggle_main_window(ah)
+    } else if action == SHORTCUT_QUICK_SEARCH {
+        send_to_main(ah, QUICK_SEARCH_EVENT, "__QUICK_SEARCH__", true)
+    } else {
+        Ok(())
+    };
+    if let Err(e) = r {
+        error!("fail to run {} shortcut. {:?}", action, e);
+    }
+}
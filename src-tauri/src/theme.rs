@@ -0,0 +1,53 @@
+use std::path::{Path, PathBuf};
+
+static THEMES_DIR: &str = "themes";
+static USER_CSS_FILE: &str = "user.css";
+static DICT_CSS_DIR: &str = "dicts";
+
+/// Inverts the page and inverts media back, which works with any dictionary
+/// stylesheet.
+static DARK_MODE_CSS: &str = "
+html { filter: invert(1) hue-rotate(180deg); background: #fff; }
+img, video, picture, canvas, svg image { filter: invert(1) hue-rotate(180deg); }
+";
+
+pub fn theme_directory(config_dir: &Path) -> PathBuf {
+    config_dir.join(THEMES_DIR)
+}
+
+/// Creates the theme directory with an empty user stylesheet so users know
+/// where to put their styles.
+pub fn init_theme_directory(config_dir: &Path) -> std::io::Result<PathBuf> {
+    let dir = theme_directory(config_dir);
+    std::fs::create_dir_all(dir.join(DICT_CSS_DIR))?;
+    let user_css = dir.join(USER_CSS_FILE);
+    if !user_css.exists() {
+        std::fs::write(
+            user_css,
+            "/* Applied to entries of all dictionaries. Put per-dictionary styles in dicts/<dictionary name>.css */\n",
+        )?;
+    }
+    Ok(dir)
+}
+
+/// Returns the global user stylesheet followed by the override stylesheet of
+/// the dictionary. The files may not exist.
+pub fn user_css_files(config_dir: &Path, dict_name: Option<&str>) -> Vec<PathBuf> {
+    let dir = theme_directory(config_dir);
+    let mut list = vec![dir.join(USER_CSS_FILE)];
+    if let Some(name) = dict_name {
+        list.push(dir.join(DICT_CSS_DIR).join(format!("{}.css", name)));
+    }
+    list
+}
+
+pub fn theme_css(dark_mode: bool, font_scale: u32) -> String {
+    let mut css = String::new();
+    if dark_mode {
+        css.push_str(DARK_MODE_CSS);
+    }
+    if font_scale != 100 && font_scale > 0 {
+        css.push_str(&format!("body {{ zoom: {}%; }}\n", font_scale));
+    }
+    css
+}
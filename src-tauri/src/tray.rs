@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use log::error;
+use tauri::{
+    menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
+    AppHandle, Manager,
+};
+
+use crate::{
+    base::AppState,
+    clipboard::lookup_clipboard,
+    database::Database,
+    error::Result,
+    lookup::{lookup, show_main_window},
+    model::history::HistoryModel,
+    settings::SHORTCUT_OCR,
+    shortcuts::register_shortcuts,
+};
+
+static TRAY_ID: &str = "main";
+static MENU_MAIN: &str = "main";
+static MENU_QUIT: &str = "quit";
+static MENU_CLIPBOARD: &str = "clipboard";
+static MENU_OCR_SHORTCUT: &str = "ocr_shortcut";
+/// Ids of recent lookups are `recent:` and the JSON of `[dict, name]`
+static RECENT_PREFIX: &str = "recent:";
+/// Ids of dictionary groups are `group:{name}`
+static GROUP_PREFIX: &str = "group:";
+static RECENT_SIZE: usize = 10;
+/// History rows read to find `RECENT_SIZE` distinct words
+static RECENT_SCAN_SIZE: usize = 50;
+
+/// Rebuilds the tray menu from the settings and the latest lookups.
+pub async fn update_tray(ah: &AppHandle) -> Result<()> {
+    let tray = match ah.tray_by_id(TRAY_ID) {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    let recent = recent_lookups(ah).await?;
+
+    let state = ah.state::<AppState>();
+    let settings_lock = state.settings.read().await;
+    let config = &settings_lock.config;
+
+    let recent_menu = Submenu::new(ah, "Recent lookups", !recent.is_empty())?;
+    for item in &recent {
+        // Dictionaries no longer loaded are left out.
+        let dict = item
+            .dict_id(&config.dicts)
+            .map(|_| item.dict.as_str())
+            .unwrap_or_default();
+        let id = format!(
+            "{}{}",
+            RECENT_PREFIX,
+            serde_json::to_string(&(dict, &item.name))?
+        );
+        recent_menu.append(&MenuItem::with_id(ah, id, &item.name, true, None::<&str>)?)?;
+    }
+
+    let group_menu = Submenu::new(ah, "Dictionary group", !config.dict_groups.is_empty())?;
+    let current_group = config.current_dict_group().map(|x| x.name.clone());
+    for group in &config.dict_groups {
+        let id = format!("{}{}", GROUP_PREFIX, group.name);
+        let checked = current_group.as_ref() == Some(&group.name);
+        group_menu.append(&CheckMenuItem::with_id(
+            ah,
+            id,
+            &group.name,
+            true,
+            checked,
+            None::<&str>,
+        )?)?;
+    }
+
+    let ocr_shortcut = config
+        .shortcuts
+        .get(SHORTCUT_OCR)
+        .cloned()
+        .unwrap_or_default();
+    let ocr_label = if ocr_shortcut.is_empty() {
+        "OCR shortcut".to_string()
+    } else {
+        format!("OCR shortcut ({})", ocr_shortcut)
+    };
+    let ocr_item = CheckMenuItem::with_id(
+        ah,
+        MENU_OCR_SHORTCUT,
+        ocr_label,
+        !ocr_shortcut.is_empty(),
+        config.ocr_shortcut_enabled,
+        None::<&str>,
+    )?;
+    drop(settings_lock);
+
+    let menu = Menu::with_items(
+        ah,
+        &[
+            &MenuItem::with_id(ah, MENU_MAIN, "Beluga", true, None::<&str>)?,
+            &PredefinedMenuItem::separator(ah)?,
+            &MenuItem::with_id(ah, MENU_CLIPBOARD, "Look up clipboard", true, None::<&str>)?,
+            &recent_menu,
+            &group_menu,
+            &ocr_item,
+            &PredefinedMenuItem::separator(ah)?,
+            &MenuItem::with_id(ah, MENU_QUIT, "Quit", true, None::<&str>)?,
+        ],
+    )?;
+    tray.set_menu(Some(menu))?;
+    Ok(())
+}
+
+/// Latest looked up words without repeats, newest first
+async fn recent_lookups(ah: &AppHandle) -> Result<Vec<HistoryModel>> {
+    // The database may not be ready right after startup
+    let db = match ah.try_state::<Arc<Database>>() {
+        Some(v) => v.inner().clone(),
+        None => return Ok(vec![]),
+    };
+    let mut conn = db.pool.acquire().await?;
+    let mut list = HistoryModel::list(&mut conn, RECENT_SCAN_SIZE).await?;
+    let mut seen: Vec<String> = vec![];
+    list.retain(|x| {
+        if seen.contains(&x.name) {
+            return false;
+        }
+        seen.push(x.name.clone());
+        true
+    });
+    list.truncate(RECENT_SIZE);
+    Ok(list)
+}
+
+pub fn handle_menu_event(ah: &AppHandle, id: &str) {
+    if id == MENU_QUIT {
+        ah.exit(0);
+        return;
+    }
+    let ah = ah.clone();
+    let id = id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = run_menu_action(&ah, &id).await {
+            error!("fail to run tray menu {}. {:?}", id, e);
+        }
+    });
+}
+
+async fn run_menu_action(ah: &AppHandle, id: &str) -> Result<()> {
+    if id == MENU_MAIN {
+        show_main_window(ah)?;
+    } else if id == MENU_CLIPBOARD {
+        lookup_clipboard(ah).await?;
+    } else if id == MENU_OCR_SHORTCUT {
+        let state = ah.state::<AppState>();
+        let mut settings_lock = state.settings.write().await;
+        settings_lock.config.ocr_shortcut_enabled = !settings_lock.config.ocr_shortcut_enabled;
+        settings_lock.save()?;
+        register_shortcuts(ah, &settings_lock.config.active_shortcuts());
+        settings_lock.notify_changed(ah.clone());
+    } else if let Some(name) = id.strip_prefix(GROUP_PREFIX) {
+        let state = ah.state::<AppState>();
+        let mut settings_lock = state.settings.write().await;
+        settings_lock.config.switch_dict_group(name)?;
+        settings_lock.save()?;
+        settings_lock.notify_changed(ah.clone());
+    } else if let Some(v) = id.strip_prefix(RECENT_PREFIX) {
+        let (dict, name): (String, String) = serde_json::from_str(v)?;
+        let dict = Some(dict.as_str()).filter(|x| !x.is_empty());
+        lookup(ah, &name, dict).await?;
+    }
+    Ok(())
+}
@@ -1,4 +1,9 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub fn current_timestamp() -> i64 {
     SystemTime::now()
@@ -6,3 +11,62 @@ pub fn current_timestamp() -> i64 {
         .unwrap()
         .as_millis() as i64
 }
+
+static TOKEN_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Returns 32 hex characters that are hard to guess, from the randomly
+/// seeded std hasher.
+pub fn random_token() -> String {
+    let mut token = String::with_capacity(32);
+    for _ in 0..2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(TOKEN_COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u128(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos(),
+        );
+        token.push_str(&format!("{:016x}", hasher.finish()));
+    }
+    token
+}
+
+/// Percent-encodes like `encodeURIComponent` in JavaScript.
+pub fn encode_uri_component(s: &str) -> String {
+    let mut r = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&b) {
+            r.push(b as char);
+        } else {
+            r.push_str(&format!("%{:02X}", b));
+        }
+    }
+    r
+}
+
+/// Matches a name against a glob where `*` matches any characters, path
+/// separators included, and `?` matches one character. Case is ignored.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let s: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut i, mut j) = (0, 0);
+    // Position after the last `*` and the position in name it matched up to
+    let mut star: Option<(usize, usize)> = None;
+    while j < s.len() {
+        if i < p.len() && (p[i] == '?' || (p[i] != '*' && p[i] == s[j])) {
+            i += 1;
+            j += 1;
+        } else if i < p.len() && p[i] == '*' {
+            i += 1;
+            star = Some((i, j));
+        } else if let Some((si, sj)) = star {
+            i = si;
+            j = sj + 1;
+            star = Some((si, sj + 1));
+        } else {
+            return false;
+        }
+    }
+    p[i..].iter().all(|c| *c == '*')
+}
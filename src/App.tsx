@@ -1,14 +1,61 @@
 import { useNavigate } from '@solidjs/router';
 import { event } from '@tauri-apps/api';
+import { getCurrentWebviewWindow } from '@tauri-apps/api/webviewWindow';
 import { ParentComponent } from 'solid-js';
+import { setFocusSearch, setOcrResult } from './state';
 
 const App: ParentComponent = (props) => {
     const navigate = useNavigate();
 
-    event.listen('ocr_text', ({ payload }) => {
-        const kw = encodeURIComponent(payload as string);
-        navigate(`/?kw=${kw}`, { replace: true });
-    });
+    function showOcrResult(r: OcrResult) {
+        setOcrResult(r);
+        if (r.hit && r.hit.text) {
+            const kw = encodeURIComponent(r.hit.text);
+            navigate(`/?kw=${kw}`, { replace: true });
+        } else {
+            navigate('/', { replace: true });
+        }
+    }
+
+    function lookup({ text, dict }: LookupRequest) {
+        setOcrResult(null);
+        let url = `/?kw=${encodeURIComponent(text)}`;
+        if (dict) {
+            url += `&dict=${encodeURIComponent(dict)}`;
+        }
+        navigate(url, { replace: true });
+    }
+
+    function quickSearch() {
+        setOcrResult(null);
+        setFocusSearch(true);
+        navigate('/', { replace: true });
+    }
+
+    // Events are delivered to every window, other windows such as the popup
+    // have their own handling.
+    if (getCurrentWebviewWindow().label === 'main') {
+        event.listen<OcrResult>('ocr_result', ({ payload }) => {
+            showOcrResult(payload);
+        });
+        // Set when the window is created for the result
+        if ('__OCR_RESULT__' in window && window.__OCR_RESULT__) {
+            showOcrResult(window.__OCR_RESULT__ as OcrResult);
+            delete window.__OCR_RESULT__;
+        }
+        event.listen<LookupRequest>('lookup', ({ payload }) => {
+            lookup(payload);
+        });
+        if ('__LOOKUP__' in window && window.__LOOKUP__) {
+            lookup(window.__LOOKUP__ as LookupRequest);
+            delete window.__LOOKUP__;
+        }
+        event.listen('quick_search', quickSearch);
+        if ('__QUICK_SEARCH__' in window && window.__QUICK_SEARCH__) {
+            quickSearch();
+            delete window.__QUICK_SEARCH__;
+        }
+    }
 
     return props.children;
 };
@@ -223,9 +223,31 @@ export function makeSearcher(
 export async function loadEntry(
     iframe: HTMLIFrameElement,
     dictId: number,
-    name: string
+    name: string,
+    nav?: 'history',
+    view?: 'popup'
 ) {
-    iframe.src = `http://localhost:${serverPort()}/@entry?dict_id=${dictId}&name=${encodeURIComponent(
+    let src = `http://localhost:${serverPort()}/@entry?dict_id=${dictId}&name=${encodeURIComponent(
         name
     )}`;
+    if (nav) {
+        src += `&nav=${nav}`;
+    }
+    if (view) {
+        src += `&view=${view}`;
+    }
+    iframe.src = src;
+}
+
+/**
+ * Plays the first pronunciation of a word found in the dictionaries.
+ * Returns false if there's none.
+ */
+export async function playPronunciation(name: string, dictId?: number) {
+    const list = await sendMessage('get_pronunciations', { name, id: dictId });
+    if (list.length === 0) {
+        return false;
+    }
+    await new Audio(list[0].url).play();
+    return true;
 }
@@ -0,0 +1,177 @@
+import {
+    Component,
+    For,
+    Show,
+    createEffect,
+    createMemo,
+    createSignal,
+} from 'solid-js';
+import './OcrText.css';
+
+interface OcrTextProps {
+    lines: OcrLine[];
+    /** The word under the mouse pointer, selected at first */
+    hit?: OcrHit | null;
+    /**
+     * Called with the word or phrase selected and the text of the lines it
+     * is in
+     */
+    onSelect: (text: string, context: string) => void;
+    onClose: () => void;
+}
+
+/**
+ * Shows recognized text by lines. Click a word to look it up, shift-click
+ * another word to look up the phrase between them. Phrases around the hit
+ * word are offered as well.
+ */
+const OcrText: Component<OcrTextProps> = (props) => {
+    // Words of all lines in reading order
+    const words = createMemo(() => props.lines.flatMap((line) => line.words));
+    const [anchor, setAnchor] = createSignal(-1);
+    const [focus, setFocus] = createSignal(-1);
+
+    function isSelected(i: number) {
+        const a = Math.min(anchor(), focus());
+        const b = Math.max(anchor(), focus());
+        return a >= 0 && i >= a && i <= b;
+    }
+
+    function select(a: number, b: number) {
+        setAnchor(a);
+        setFocus(b);
+        const list = words().slice(a, b + 1);
+        const phrase = list.map((x) => x.text).join(' ');
+        // Lines spanned by the selection
+        const first = lineOf(a);
+        const last = lineOf(b);
+        const context = props.lines
+            .slice(first, last + 1)
+            .map((x) => x.text)
+            .join(' ');
+        props.onSelect(phrase, context);
+    }
+
+    function clickWord(i: number, e: MouseEvent) {
+        if (e.shiftKey && anchor() >= 0) {
+            const a = anchor();
+            select(Math.min(a, i), Math.max(a, i));
+            setAnchor(a);
+            setFocus(i);
+        } else {
+            select(i, i);
+        }
+    }
+
+    // Index of the first word of each line in words()
+    const lineOffsets = createMemo(() => {
+        let offset = 0;
+        return props.lines.map((line) => {
+            const v = offset;
+            offset += line.words.length;
+            return v;
+        });
+    });
+
+    function lineOf(i: number) {
+        const offsets = lineOffsets();
+        let line = 0;
+        while (line + 1 < offsets.length && offsets[line + 1] <= i) {
+            line++;
+        }
+        return line;
+    }
+
+    // Highlight the hit word without looking it up, which is done already
+    createEffect(() => {
+        const hit = props.hit;
+        if (hit && hit.line < props.lines.length) {
+            const i = lineOffsets()[hit.line] + hit.word;
+            setAnchor(i);
+            setFocus(i);
+        } else {
+            setAnchor(-1);
+            setFocus(-1);
+        }
+    });
+
+    // Phrases of 2 or 3 words containing the hit word within its line
+    const phrases = createMemo(() => {
+        const hit = props.hit;
+        const line = hit && props.lines[hit.line];
+        if (!hit || !line) {
+            return [];
+        }
+        const offset = lineOffsets()[hit.line];
+        const list: { text: string; start: number; end: number }[] = [];
+        for (let len = 2; len <= 3; len++) {
+            for (let start = hit.word - len + 1; start <= hit.word; start++) {
+                if (start < 0 || start + len > line.words.length) {
+                    continue;
+                }
+                const text = line.words
+                    .slice(start, start + len)
+                    .map((x) => x.text)
+                    .join(' ');
+                list.push({
+                    text,
+                    start: offset + start,
+                    end: offset + start + len - 1,
+                });
+            }
+        }
+        return list;
+    });
+
+    return (
+        <div class="ocr-text d-flex p-2 bg-light-subtle">
+            <div class="flex-grow-1">
+                <For each={props.lines}>
+                    {(line, li) => (
+                        <div class="ocr-line">
+                            <For each={line.words}>
+                                {(wd, wi) => {
+                                    const i = () => lineOffsets()[li()] + wi();
+                                    return (
+                                        <span
+                                            class="ocr-word"
+                                            classList={{
+                                                selected: isSelected(i()),
+                                            }}
+                                            onClick={(e) => clickWord(i(), e)}
+                                        >
+                                            {wd.text}
+                                        </span>
+                                    );
+                                }}
+                            </For>
+                        </div>
+                    )}
+                </For>
+                <Show when={phrases().length > 0}>
+                    <div class="ocr-phrases mt-1">
+                        <For each={phrases()}>
+                            {(p) => (
+                                <button
+                                    class="btn btn-sm btn-outline-secondary me-1 mb-1"
+                                    onClick={() => select(p.start, p.end)}
+                                >
+                                    {p.text}
+                                </button>
+                            )}
+                        </For>
+                    </div>
+                </Show>
+            </div>
+            <button
+                class="btn btn-sm btn-light flex-shrink-0 align-self-start"
+                title="Close"
+                onClick={() => props.onClose()}
+            >
+                <i class="bi bi-x-lg"></i>
+            </button>
+        </div>
+    );
+};
+
+export default OcrText;
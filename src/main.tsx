@@ -14,6 +14,8 @@ import { setAppConfig, setServerPort } from './state';
 import { event } from '@tauri-apps/api';
 import Words from './pages/Words';
 import Book from './pages/Book';
+import OcrOverlay from './pages/OcrOverlay';
+import Popup from './pages/Popup';
 
 render(
     () => (
@@ -22,6 +24,8 @@ render(
             <Route path="/settings" component={Settings} />
             <Route path="/books" component={Book}></Route>
             <Route path="/book/:id" component={Words}></Route>
+            <Route path="/ocr-overlay" component={OcrOverlay}></Route>
+            <Route path="/popup" component={Popup}></Route>
         </Router>
     ),
     document.getElementById('root')!
@@ -49,3 +53,10 @@ sendMessage('get_settings', undefined).then((v) => {
 sendMessage('get_server_port', undefined).then((port) => {
     setServerPort(port);
 });
+
+// The server may be listening after the port is asked for
+event.listen<ServerStatus>('server_status', ({ payload }) => {
+    if (payload.port) {
+        setServerPort(payload.port);
+    }
+});
@@ -1,16 +1,27 @@
 import {
     Component,
     For,
+    Show,
     batch,
     createEffect,
     createMemo,
     createSignal,
+    onCleanup,
 } from 'solid-js';
 import './Home.css';
 import { Word, debounce, loadEntry, makeSearcher, sendMessage } from '../base';
 import { A, useSearchParams } from '@solidjs/router';
 import poptip from 'poptip';
-import { appConfig } from '../state';
+import { event } from '@tauri-apps/api';
+import {
+    appConfig,
+    focusSearch,
+    ocrResult,
+    setFocusSearch,
+    setOcrResult,
+} from '../state';
+import OcrText from '../components/OcrText';
+import * as dialog from '@tauri-apps/plugin-dialog';
 
 const Home: Component = () => {
     const [keyword, setKeyword] = createSignal('');
@@ -18,18 +29,21 @@ const Home: Component = () => {
 
     let kwInput!: HTMLInputElement;
     const [searchParams, _] = useSearchParams();
+    // Dictionary a lookup from another app asks for, its entry is selected
+    // when found
+    let preferredDict: string | null = null;
     function searchByUrlParam(keyword?: string) {
         const kw = keyword ?? decodeURIComponent(searchParams.kw as string);
+        preferredDict = keyword
+            ? null
+            : ((searchParams.dict as string | undefined) ?? null);
         setTimeout(() => {
             kwInput.value = kw;
             kwInput.dispatchEvent(new Event('input', { bubbles: true }));
             kwInput.focus();
         }, 100);
     }
-    if ('__OCR_TEXT__' in window && window.__OCR_TEXT__) {
-        searchByUrlParam(window.__OCR_TEXT__ as string);
-        delete window.__OCR_TEXT__;
-    } else if (searchParams.kw) {
+    if (searchParams.kw) {
         searchByUrlParam();
     }
     createEffect(() => {
@@ -37,9 +51,73 @@ const Home: Component = () => {
             searchByUrlParam();
         }
     });
+    createEffect(() => {
+        if (focusSearch()) {
+            setFocusSearch(false);
+            setTimeout(() => {
+                kwInput.focus();
+                kwInput.select();
+            }, 100);
+        }
+    });
 
     let iframe!: HTMLIFrameElement;
 
+    type HistoryItem = { dictId: number; name: string };
+    let current: HistoryItem | null = null;
+    const [backStack, setBackStack] = createSignal<HistoryItem[]>([]);
+    const [forwardStack, setForwardStack] = createSignal<HistoryItem[]>([]);
+
+    // Every entry shown is reported by the server, including the ones reached
+    // by links inside entries. Keep the search box in sync with the headword
+    // actually shown, which differs from the selected word after redirects.
+    const unlisten = event.listen<EntryViewed>('entry_viewed', ({ payload }) => {
+        // Entries of the popup window are not part of this page
+        if (payload.view === 'popup') {
+            return;
+        }
+        const item = { dictId: payload.dict_id, name: payload.name };
+        if (payload.nav !== 'history') {
+            if (
+                current &&
+                (current.dictId !== item.dictId || current.name !== item.name)
+            ) {
+                setBackStack([...backStack(), current]);
+                setForwardStack([]);
+            }
+        }
+        current = item;
+        kwInput.value = item.name;
+        setKeyword(item.name);
+    });
+    onCleanup(() => unlisten.then((f) => f()));
+
+    function goBack() {
+        const stack = backStack();
+        if (stack.length === 0 || !current) {
+            return;
+        }
+        const item = stack[stack.length - 1];
+        batch(() => {
+            setBackStack(stack.slice(0, -1));
+            setForwardStack([...forwardStack(), current!]);
+        });
+        loadEntry(iframe, item.dictId, item.name, 'history');
+    }
+
+    function goForward() {
+        const stack = forwardStack();
+        if (stack.length === 0 || !current) {
+            return;
+        }
+        const item = stack[stack.length - 1];
+        batch(() => {
+            setForwardStack(stack.slice(0, -1));
+            setBackStack([...backStack(), current!]);
+        });
+        loadEntry(iframe, item.dictId, item.name, 'history');
+    }
+
     const { search, selectedWord, setSelectedWord, searchResult } =
         makeSearcher(false, appConfig.prefix_limit, appConfig.phrase_limit);
     const result = createMemo(() => [
@@ -55,6 +133,14 @@ const Home: Component = () => {
         }
     });
 
+    createEffect(() => {
+        const wd = result().find((x) => x.dict === preferredDict);
+        if (wd) {
+            preferredDict = null;
+            setSelectedWord(wd);
+        }
+    });
+
     const searchWord = debounce(() => {
         search(keyword());
     }, 500);
@@ -99,11 +185,71 @@ const Home: Component = () => {
         selectResult(list[idx]);
     }
 
+    // The OCR line of the selected word, saved with words added to the book
+    const [ocrContext, setOcrContext] = createSignal<string | null>(null);
+    createEffect(() => {
+        const r = ocrResult();
+        setOcrContext(r && r.hit ? r.lines[r.hit.line].text : null);
+    });
+
     async function addToBook(name: string) {
-        await sendMessage('add_word', [0, name]);
+        await sendMessage('add_word', {
+            book_id: 0,
+            name,
+            context: ocrResult() ? ocrContext() : null,
+        });
         poptip.info({ html: `<b>${name}</b> is added` });
     }
 
+    async function copyEntryText(wd: Word) {
+        const text = await sendMessage('get_entry_text', {
+            id: wd.id,
+            name: wd.name,
+        });
+        if (text === null) {
+            return poptip.error('No entry found');
+        }
+        await navigator.clipboard.writeText(text);
+        poptip.info('Copied');
+    }
+
+    async function recognizeImage(fromClipboard: boolean) {
+        try {
+            let r: OcrResult;
+            if (fromClipboard) {
+                r = await sendMessage('ocr_clipboard_image', undefined);
+            } else {
+                const file = await dialog.open({
+                    multiple: false,
+                    filters: [
+                        {
+                            name: 'Image',
+                            extensions: [
+                                'png',
+                                'jpg',
+                                'jpeg',
+                                'bmp',
+                                'webp',
+                                'gif',
+                                'tiff',
+                            ],
+                        },
+                    ],
+                });
+                if (typeof file !== 'string') {
+                    return;
+                }
+                r = await sendMessage('ocr_image_file', file);
+            }
+            if (r.lines.length === 0) {
+                return poptip.info('No text found');
+            }
+            setOcrResult(r);
+        } catch (e) {
+            poptip.error(`fail to recognize image.\n${e}`);
+        }
+    }
+
     function showShortcut() {
         poptip.info(
             {
@@ -111,6 +257,7 @@ const Home: Component = () => {
                     '<p><kbd>Tab</kbd> Next result</p>',
                     '<p><kbd>Shift</kbd> + <kbd>Tab</kbd> Previous result</p>',
                     '<p><kbd>Shift</kbd> + <kbd>Backspace</kbd> Clear keyword</p>',
+                    '<p><kbd>Alt</kbd> + <kbd>←</kbd> / <kbd>→</kbd> Back / Forward</p>',
                     '<p class="mb-0"><kbd>Shift</kbd> + <kbd>Enter</kbd> Add to word book</p>',
                 ].join(''),
             },
@@ -124,6 +271,22 @@ const Home: Component = () => {
                 <A href="/books" class="btn btn-light me-2">
                     <i class="bi bi-star-fill"></i>
                 </A>
+                <button
+                    class="btn btn-light"
+                    title="Back"
+                    disabled={backStack().length === 0}
+                    onClick={goBack}
+                >
+                    <i class="bi bi-arrow-left"></i>
+                </button>
+                <button
+                    class="btn btn-light me-2"
+                    title="Forward"
+                    disabled={forwardStack().length === 0}
+                    onClick={goForward}
+                >
+                    <i class="bi bi-arrow-right"></i>
+                </button>
                 <div class="flex-grow-1 position-relative">
                     <button
                         class="btn me-2 toggle-words"
@@ -139,6 +302,9 @@ const Home: Component = () => {
                         value={keyword()}
                         ref={kwInput}
                         onInput={(e) => {
+                            if (e.isTrusted) {
+                                preferredDict = null;
+                            }
                             setKeyword(e.target.value);
                             searchWord();
                         }}
@@ -156,6 +322,12 @@ const Home: Component = () => {
                                     setKeyword('');
                                     searchWord();
                                 }
+                            } else if (e.key === 'ArrowLeft' && e.altKey) {
+                                e.preventDefault();
+                                goBack();
+                            } else if (e.key === 'ArrowRight' && e.altKey) {
+                                e.preventDefault();
+                                goForward();
                             } else if (e.key === 'Enter') {
                                 if (e.shiftKey) {
                                     e.preventDefault();
@@ -174,10 +346,54 @@ const Home: Component = () => {
                         <i class="bi bi-keyboard-fill"></i>
                     </button>
                 </div>
+                <div class="dropdown ms-2">
+                    <button
+                        type="button"
+                        class="btn btn-light"
+                        title="Recognize text in an image"
+                        data-bs-toggle="dropdown"
+                        aria-expanded="false"
+                    >
+                        <i class="bi bi-image"></i>
+                    </button>
+                    <ul class="dropdown-menu dropdown-menu-end">
+                        <li>
+                            <a
+                                class="dropdown-item"
+                                href="#"
+                                onClick={() => recognizeImage(false)}
+                            >
+                                Image file...
+                            </a>
+                        </li>
+                        <li>
+                            <a
+                                class="dropdown-item"
+                                href="#"
+                                onClick={() => recognizeImage(true)}
+                            >
+                                Image on clipboard
+                            </a>
+                        </li>
+                    </ul>
+                </div>
                 <A href="/settings" class="btn btn-light ms-2">
                     <i class="bi bi-gear-wide-connected"></i>
                 </A>
             </header>
+            <Show when={ocrResult()}>
+                {(r) => (
+                    <OcrText
+                        lines={r().lines}
+                        hit={r().hit}
+                        onSelect={(text, context) => {
+                            setOcrContext(context);
+                            searchByUrlParam(text);
+                        }}
+                        onClose={() => setOcrResult(null)}
+                    />
+                )}
+            </Show>
             <div class="flex-grow-1 search-result">
                 <ul
                     class="search-words"
@@ -199,6 +415,16 @@ const Home: Component = () => {
                                     <p class="result-name">{item.name}</p>
                                     <p class="result-dict">{item.dict}</p>
                                 </div>
+                                <button
+                                    title="Copy as text"
+                                    class="btn opacity-75 my-2"
+                                    onClick={(e) => {
+                                        e.stopPropagation();
+                                        copyEntryText(item);
+                                    }}
+                                >
+                                    <i class="bi bi-clipboard"></i>
+                                </button>
                                 <button
                                     title="Add to word book"
                                     class="btn opacity-75 m-2"
@@ -0,0 +1,114 @@
+import { Component, Show, createSignal, onCleanup, onMount } from 'solid-js';
+import './OcrOverlay.css';
+import { sendMessage } from '../base';
+
+type Point = { x: number; y: number };
+
+/** Transparent full screen page to select a region to OCR */
+const OcrOverlay: Component = () => {
+    const [start, setStart] = createSignal<Point | null>(null);
+    const [end, setEnd] = createSignal<Point | null>(null);
+    const [busy, setBusy] = createSignal(false);
+
+    function rect() {
+        const a = start();
+        const b = end();
+        if (!a || !b) {
+            return null;
+        }
+        return {
+            x: Math.min(a.x, b.x),
+            y: Math.min(a.y, b.y),
+            width: Math.abs(a.x - b.x),
+            height: Math.abs(a.y - b.y),
+        };
+    }
+
+    function onMouseDown(e: MouseEvent) {
+        if (busy() || e.button !== 0) {
+            return;
+        }
+        setStart({ x: e.clientX, y: e.clientY });
+        setEnd({ x: e.clientX, y: e.clientY });
+    }
+
+    function onMouseMove(e: MouseEvent) {
+        if (start() && !busy()) {
+            setEnd({ x: e.clientX, y: e.clientY });
+        }
+    }
+
+    async function onMouseUp() {
+        const r = rect();
+        if (!r || busy()) {
+            return;
+        }
+        if (r.width < 4 || r.height < 4) {
+            setStart(null);
+            setEnd(null);
+            return;
+        }
+        setBusy(true);
+        try {
+            await sendMessage('ocr_region', {
+                ...r,
+                view_width: window.innerWidth,
+                view_height: window.innerHeight,
+            });
+        } catch (e) {
+            console.error('fail to recognize region', e);
+            close();
+        }
+    }
+
+    function close() {
+        sendMessage('close_ocr_overlay', undefined);
+    }
+
+    function onKeyDown(e: KeyboardEvent) {
+        if (e.key === 'Escape') {
+            close();
+        }
+    }
+    onMount(() => {
+        // The window is transparent, so should be the page
+        document.documentElement.style.background = 'transparent';
+        document.body.style.background = 'transparent';
+    });
+    document.addEventListener('keydown', onKeyDown);
+    onCleanup(() => document.removeEventListener('keydown', onKeyDown));
+
+    return (
+        <div
+            class="ocr-overlay"
+            onMouseDown={onMouseDown}
+            onMouseMove={onMouseMove}
+            onMouseUp={onMouseUp}
+            onContextMenu={(e) => {
+                e.preventDefault();
+                close();
+            }}
+        >
+            <div class="ocr-hint">
+                {busy()
+                    ? 'Recognizing...'
+                    : 'Drag to select text, Esc to cancel'}
+            </div>
+            <Show when={rect()}>
+                {(r) => (
+                    <div
+                        class="ocr-selection"
+                        style={{
+                            left: `${r().x}px`,
+                            top: `${r().y}px`,
+                            width: `${r().width}px`,
+                            height: `${r().height}px`,
+                        }}
+                    ></div>
+                )}
+            </Show>
+        </div>
+    );
+};
+
+export default OcrOverlay;
@@ -0,0 +1,95 @@
+import { Component, Show, createSignal, onCleanup } from 'solid-js';
+import { event } from '@tauri-apps/api';
+import './Popup.css';
+import { loadEntry, sendMessage } from '../base';
+import { appConfig, setAppConfig } from '../state';
+
+/** Small window next to the mouse pointer showing the entry of a word */
+const Popup: Component = () => {
+    const [word, setWord] = createSignal('');
+    const [dictName, setDictName] = createSignal('');
+    const [notFound, setNotFound] = createSignal(false);
+    let iframe!: HTMLIFrameElement;
+    let seq = 0;
+
+    // Shows the entry of the first available dictionary that has the word,
+    // trying the requested dictionary first
+    async function lookup({ text, dict: preferred }: LookupRequest) {
+        const current = ++seq;
+        setWord(text);
+        setDictName('');
+        setNotFound(false);
+        const dicts = appConfig.dicts.filter((x) => x.available);
+        dicts.sort(
+            (a, b) => +(b.name === preferred) - +(a.name === preferred)
+        );
+        for (const dict of dicts) {
+            let list: string[] = [];
+            try {
+                list = await sendMessage('search', {
+                    id: dict.id,
+                    kw: text,
+                    strict: true,
+                    prefix_limit: 1,
+                    phrase_limit: 0,
+                });
+            } catch (e) {
+                console.error('fail to search', dict.name, e);
+            }
+            if (current !== seq) {
+                return;
+            }
+            if (list.length > 0) {
+                setDictName(dict.name);
+                loadEntry(iframe, dict.id, list[0], undefined, 'popup');
+                return;
+            }
+        }
+        setNotFound(true);
+    }
+
+    const unlisten = event.listen<LookupRequest>(
+        'popup_lookup',
+        ({ payload }) => {
+            lookup(payload);
+        }
+    );
+    onCleanup(() => unlisten.then((f) => f()));
+    // Set when the window is created for the first lookup
+    if ('__POPUP_LOOKUP__' in window && window.__POPUP_LOOKUP__) {
+        const req = window.__POPUP_LOOKUP__ as LookupRequest;
+        delete window.__POPUP_LOOKUP__;
+        // Dictionaries are known once the settings are loaded
+        sendMessage('get_settings', undefined).then((v) => {
+            setAppConfig(v);
+            lookup(req);
+        });
+    }
+
+    function onKeyDown(e: KeyboardEvent) {
+        if (e.key === 'Escape') {
+            sendMessage('close_popup', undefined);
+        }
+    }
+    document.addEventListener('keydown', onKeyDown);
+    onCleanup(() => document.removeEventListener('keydown', onKeyDown));
+
+    return (
+        <div class="popup">
+            <div class="popup-header">
+                <span class="fw-bold text-truncate">{word()}</span>
+                <span class="text-secondary text-truncate">{dictName()}</span>
+            </div>
+            <Show when={notFound()}>
+                <div class="popup-empty text-secondary">No entry found</div>
+            </Show>
+            <iframe
+                ref={iframe}
+                class="popup-entry"
+                classList={{ 'd-none': notFound() || !dictName() }}
+            ></iframe>
+        </div>
+    );
+};
+
+export default Popup;
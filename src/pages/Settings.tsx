@@ -1,13 +1,41 @@
-import { Component, For } from 'solid-js';
+import {
+    Component,
+    For,
+    Match,
+    Show,
+    Switch,
+    createMemo,
+    createSignal,
+    onCleanup,
+} from 'solid-js';
 import { appConfig, setAppConfig } from '../state';
 import { sendMessage } from '../base';
 import poptip from 'poptip';
 import BackPage from '../components/BackPage';
 import * as dialog from '@tauri-apps/plugin-dialog';
 import * as shell from '@tauri-apps/plugin-shell';
-import { register, unregister } from '@tauri-apps/plugin-global-shortcut';
+import { event } from '@tauri-apps/api';
 
 const Settings: Component = () => {
+    const [shortcutStatus, setShortcutStatus] = createSignal<
+        ShortcutStatus[]
+    >([]);
+    sendMessage('get_shortcut_status', undefined).then(setShortcutStatus);
+    const unlistenShortcut = event.listen<ShortcutStatus[]>(
+        'shortcut_status',
+        ({ payload }) => {
+            setShortcutStatus(payload);
+        }
+    );
+    onCleanup(() => unlistenShortcut.then((f) => f()));
+
+    const [ocrStatus, setOcrStatus] = createSignal<OcrStatus | null>(null);
+    sendMessage('get_ocr_status', undefined).then(setOcrStatus);
+    const unlisten = event.listen<OcrStatus>('ocr_status', ({ payload }) => {
+        setOcrStatus(payload);
+    });
+    onCleanup(() => unlisten.then((f) => f()));
+
     async function openDictDir() {
         await shell.open(appConfig.dict_dir);
     }
@@ -26,6 +54,62 @@ const Settings: Component = () => {
         poptip.info('Settings saved');
     }
 
+    async function toggleDictTrusted(index: number) {
+        const item = appConfig.dicts[index];
+        setAppConfig('dicts', index, 'trusted', !item.trusted);
+        await sendMessage('set_settings', { dicts: appConfig.dicts });
+        poptip.info('Settings saved');
+    }
+
+    // The group whose dictionaries are exactly the available ones
+    const currentDictGroup = createMemo(() =>
+        appConfig.dict_groups.find((g) =>
+            appConfig.dicts.every(
+                (x) => x.available === g.dicts.includes(x.name)
+            )
+        )
+    );
+
+    let groupNameInput!: HTMLInputElement;
+    async function addDictGroup() {
+        const name = groupNameInput.value.trim();
+        if (!name) {
+            return poptip.error('Group name is empty');
+        }
+        const group = {
+            name,
+            dicts: appConfig.dicts
+                .filter((x) => x.available)
+                .map((x) => x.name),
+        };
+        const groups = [
+            ...appConfig.dict_groups.filter((x) => x.name !== name),
+            group,
+        ];
+        await sendMessage('set_settings', { dict_groups: groups });
+        groupNameInput.value = '';
+        poptip.info('Settings saved');
+    }
+
+    async function deleteDictGroup(name: string) {
+        const groups = appConfig.dict_groups.filter((x) => x.name !== name);
+        await sendMessage('set_settings', { dict_groups: groups });
+        poptip.info('Settings saved');
+    }
+
+    async function switchDictGroup(name: string) {
+        await sendMessage('set_settings', { dict_group: name });
+        poptip.info('Settings saved');
+    }
+
+    async function toggleSanitizeEntries() {
+        setAppConfig('sanitize_entries', !appConfig.sanitize_entries);
+        await sendMessage('set_settings', {
+            sanitize_entries: appConfig.sanitize_entries,
+        });
+        poptip.info('Settings saved');
+    }
+
     async function sortDicts(i: number, up: boolean) {
         const dicts = appConfig.dicts;
         let dl: DictItem[] = [];
@@ -82,12 +166,59 @@ const Settings: Component = () => {
         poptip.info('Settings saved');
     }
 
-    async function changeOCRShortcut(shortcut: string) {
-        await unregister(appConfig.ocr_shortcut);
-        await register(shortcut, () => {
-            //
+    async function changeShortcut(action: ShortcutAction, shortcut: string) {
+        const shortcuts = { ...appConfig.shortcuts, [action]: shortcut.trim() };
+        try {
+            await sendMessage('set_settings', { shortcuts });
+        } catch (e) {
+            return poptip.error(`${e}`);
+        }
+        setAppConfig('shortcuts', shortcuts);
+        poptip.info('Settings saved');
+    }
+
+    function shortcutError(action: ShortcutAction) {
+        return shortcutStatus().find((x) => x.action === action)?.error;
+    }
+
+    async function toggleOCRShortcut() {
+        setAppConfig('ocr_shortcut_enabled', !appConfig.ocr_shortcut_enabled);
+        await sendMessage('set_settings', {
+            ocr_shortcut_enabled: appConfig.ocr_shortcut_enabled,
+        });
+        poptip.info('Settings saved');
+    }
+
+    async function togglePopupLookup() {
+        setAppConfig('popup_lookup', !appConfig.popup_lookup);
+        await sendMessage('set_settings', {
+            popup_lookup: appConfig.popup_lookup,
+        });
+        poptip.info('Settings saved');
+    }
+
+    async function toggleClipboardWatch() {
+        setAppConfig('clipboard_watch', !appConfig.clipboard_watch);
+        await sendMessage('set_settings', {
+            clipboard_watch: appConfig.clipboard_watch,
         });
-        await sendMessage('set_settings', { ocr_shortcut: shortcut });
+        poptip.info('Settings saved');
+    }
+
+    async function changeClipboardMaxLength(n: number) {
+        if (Number.isNaN(n) || n < 0) {
+            return poptip.error('invalid length');
+        }
+        await sendMessage('set_settings', { clipboard_max_length: n });
+        poptip.info('Settings saved');
+    }
+
+    async function toggleClipboardScript(script: ClipboardScript) {
+        const list = appConfig.clipboard_scripts.includes(script)
+            ? appConfig.clipboard_scripts.filter((x) => x !== script)
+            : [...appConfig.clipboard_scripts, script];
+        setAppConfig('clipboard_scripts', list);
+        await sendMessage('set_settings', { clipboard_scripts: list });
         poptip.info('Settings saved');
     }
 
@@ -102,12 +233,118 @@ const Settings: Component = () => {
         poptip.info('Settings saved');
     }
 
+    async function changeOCRMode(mode: Configuration['ocr_mode']) {
+        setAppConfig('ocr_mode', mode);
+        await sendMessage('set_settings', { ocr_mode: mode });
+        poptip.info('Settings saved');
+    }
+
+    async function chooseOCRModel(
+        key: 'ocr_detection_model' | 'ocr_recognition_model'
+    ) {
+        const file = await dialog.open({
+            multiple: false,
+            filters: [{ name: 'Model', extensions: ['rten'] }],
+        });
+        if (typeof file !== 'string') {
+            return;
+        }
+        await changeOCRModel(key, file);
+    }
+
+    async function changeOCRModel(
+        key: 'ocr_detection_model' | 'ocr_recognition_model',
+        file: string
+    ) {
+        setAppConfig(key, file);
+        setOcrStatus({ loaded: false, error: null });
+        await sendMessage(
+            'set_settings',
+            key === 'ocr_detection_model'
+                ? { ocr_detection_model: file }
+                : { ocr_recognition_model: file }
+        );
+        poptip.info('Settings saved');
+    }
+
+    type OCRToggle =
+        | 'ocr_grayscale'
+        | 'ocr_auto_invert'
+        | 'ocr_upscale'
+        | 'ocr_normalize_contrast'
+        | 'ocr_dpi_aware_crop'
+        | 'ocr_debug';
+
+    async function toggleOCROption(key: OCRToggle) {
+        setAppConfig(key, !appConfig[key]);
+        await sendMessage('set_settings', {
+            [key]: appConfig[key],
+        } as Partial<Configuration>);
+        poptip.info('Settings saved');
+    }
+
     async function toggleDevMode() {
         setAppConfig('dev_mode', !appConfig.dev_mode);
         await sendMessage('set_settings', { dev_mode: appConfig.dev_mode });
         poptip.info('Settings saved');
     }
 
+    async function toggleDarkMode() {
+        setAppConfig('dark_mode', !appConfig.dark_mode);
+        await sendMessage('set_settings', { dark_mode: appConfig.dark_mode });
+        poptip.info('Settings saved');
+    }
+
+    async function changeFontScale(scale: number) {
+        if (Number.isNaN(scale) || scale < 50 || scale > 300) {
+            return poptip.error('font size should be between 50% and 300%');
+        }
+        setAppConfig('font_scale', scale);
+        await sendMessage('set_settings', { font_scale: scale });
+        poptip.info('Settings saved');
+    }
+
+    async function openThemeDir() {
+        const dir = await sendMessage('get_theme_dir', undefined);
+        await shell.open(dir);
+    }
+
+    async function toggleDictServer() {
+        setAppConfig('dict_server', !appConfig.dict_server);
+        await sendMessage('set_settings', {
+            dict_server: appConfig.dict_server,
+        });
+        poptip.info('Settings saved, restart to take effect');
+    }
+
+    const [serverStatus, setServerStatus] = createSignal<ServerStatus | null>(
+        null
+    );
+    sendMessage('get_server_status', undefined).then(setServerStatus);
+    const unlistenServer = event.listen<ServerStatus>(
+        'server_status',
+        ({ payload }) => {
+            setServerStatus(payload);
+        }
+    );
+    onCleanup(() => unlistenServer.then((f) => f()));
+
+    async function changeHttpPort(port: number) {
+        if (Number.isNaN(port) || port < 0 || port > 65535) {
+            return poptip.error('invalid port');
+        }
+        await sendMessage('set_settings', { http_port: port });
+        poptip.info('Settings saved, restart to take effect');
+    }
+
+    async function changeDictServerPort(port: number) {
+        if (Number.isNaN(port) || port <= 0 || port > 65535) {
+            return poptip.error('invalid port');
+        }
+        await sendMessage('set_settings', { dict_server_port: port });
+        poptip.info('Settings saved, restart to take effect');
+    }
+
     return (
         <BackPage title="Settings">
             <div class="responsive-wrapper">
@@ -158,6 +395,18 @@ const Settings: Component = () => {
                                         </label>
                                     </div>
                                     <div class="flex-shrink-0">
+                                        <button
+                                            class="btn btn-sm btn-light me-2"
+                                            classList={{
+                                                'opacity-25': !item.trusted,
+                                            }}
+                                            title="Trust scripts of this dictionary"
+                                            onClick={() =>
+                                                toggleDictTrusted(index())
+                                            }
+                                        >
+                                            <i class="bi bi-shield-check"></i>
+                                        </button>
                                         <button
                                             class="btn btn-sm btn-light"
                                             onClick={() =>
@@ -184,6 +433,64 @@ const Settings: Component = () => {
                             )}
                         </For>
                     </ul>
+                    <div class="d-flex flex-wrap gap-2 mt-2">
+                        <For each={appConfig.dict_groups}>
+                            {(group) => (
+                                <div class="btn-group btn-group-sm">
+                                    <button
+                                        class="btn btn-outline-primary"
+                                        classList={{
+                                            active:
+                                                currentDictGroup()?.name ===
+                                                group.name,
+                                        }}
+                                        title="Use the dictionaries of this group"
+                                        onClick={() =>
+                                            switchDictGroup(group.name)
+                                        }
+                                    >
+                                        {group.name}
+                                    </button>
+                                    <button
+                                        class="btn btn-outline-secondary"
+                                        title="Delete group"
+                                        onClick={() =>
+                                            deleteDictGroup(group.name)
+                                        }
+                                    >
+                                        <i class="bi bi-x"></i>
+                                    </button>
+                                </div>
+                            )}
+                        </For>
+                    </div>
+                    <div class="input-group input-group-sm mt-2">
+                        <input
+                            type="text"
+                            class="form-control"
+                            placeholder="Group name"
+                            ref={groupNameInput}
+                        />
+                        <button
+                            class="btn btn-outline-secondary"
+                            onClick={addDictGroup}
+                        >
+                            Save enabled dictionaries as a group
+                        </button>
+                    </div>
+                    <div class="form-check mt-2">
+                        <input
+                            class="form-check-input"
+                            type="checkbox"
+                            checked={appConfig.sanitize_entries}
+                            id="sanitize-entries"
+                            onChange={toggleSanitizeEntries}
+                        />
+                        <label class="form-check-label" for="sanitize-entries">
+                            Remove scripts and external content from entries
+                            of untrusted dictionaries
+                        </label>
+                    </div>
                 </div>
                 <div class="mt-3">
                     <h6 class="form-label">Result</h6>
@@ -214,8 +521,143 @@ const Settings: Component = () => {
                         />
                     </div>
                 </div>
+                <div class="mt-3">
+                    <h6 class="form-label">Appearance</h6>
+                    <div class="form-check mb-3">
+                        <input
+                            class="form-check-input"
+                            type="checkbox"
+                            checked={appConfig.dark_mode}
+                            id="dark-mode"
+                            onChange={toggleDarkMode}
+                        />
+                        <label class="form-check-label" for="dark-mode">
+                            Dark mode for entries
+                        </label>
+                    </div>
+                    <div class="input-group mb-3">
+                        <span class="input-group-text">Font size</span>
+                        <input
+                            type="number"
+                            class="form-control"
+                            value={appConfig.font_scale}
+                            min={50}
+                            max={300}
+                            step={10}
+                            onChange={(e) => {
+                                changeFontScale(+e.target.value);
+                            }}
+                        />
+                        <span class="input-group-text">%</span>
+                    </div>
+                    <button
+                        class="btn btn-outline-secondary btn-sm"
+                        onClick={openThemeDir}
+                    >
+                        Open theme folder
+                    </button>
+                    <div class="form-text">
+                        user.css applies to all dictionaries,
+                        dicts/&lt;dictionary name&gt;.css to one dictionary.
+                    </div>
+                </div>
+                <div class="mt-3">
+                    <h6 class="form-label">Shortcuts</h6>
+                    <For
+                        each={
+                            [
+                                ['ocr', 'OCR'],
+                                ['clipboard', 'Look up clipboard'],
+                                ['toggle_window', 'Show / hide window'],
+                                ['quick_search', 'Quick search'],
+                            ] as const
+                        }
+                    >
+                        {([action, label]) => (
+                            <div class="mb-3">
+                                <div class="input-group">
+                                    <span class="input-group-text">
+                                        {label}
+                                    </span>
+                                    <input
+                                        type="text"
+                                        class="form-control"
+                                        classList={{
+                                            'is-invalid': !!shortcutError(action),
+                                        }}
+                                        placeholder="Disabled"
+                                        value={appConfig.shortcuts[action]}
+                                        onChange={(e) =>
+                                            changeShortcut(
+                                                action,
+                                                e.target.value
+                                            )
+                                        }
+                                    />
+                                </div>
+                                <Show when={shortcutError(action)}>
+                                    {(err) => (
+                                        <div class="form-text text-danger">
+                                            {err()}
+                                        </div>
+                                    )}
+                                </Show>
+                            </div>
+                        )}
+                    </For>
+                    <div class="form-text">
+                        For example Alt+X or CommandOrControl+Shift+D, empty to
+                        disable.
+                    </div>
+                    <div class="form-check mt-2">
+                        <input
+                            class="form-check-input"
+                            type="checkbox"
+                            checked={appConfig.ocr_shortcut_enabled}
+                            id="ocr-shortcut-enabled"
+                            onChange={toggleOCRShortcut}
+                        />
+                        <label
+                            class="form-check-label"
+                            for="ocr-shortcut-enabled"
+                        >
+                            Enable the OCR shortcut
+                        </label>
+                    </div>
+                    <div class="form-check">
+                        <input
+                            class="form-check-input"
+                            type="checkbox"
+                            checked={appConfig.popup_lookup}
+                            id="popup-lookup"
+                            onChange={togglePopupLookup}
+                        />
+                        <label class="form-check-label" for="popup-lookup">
+                            Show lookups in a popup next to the mouse pointer
+                        </label>
+                    </div>
+                </div>
                 <div class="mt-3">
                     <h6 class="form-label">OCR</h6>
+                    <div class="input-group mb-3">
+                        <span class="input-group-text">Mode</span>
+                        <select
+                            class="form-select"
+                            value={appConfig.ocr_mode}
+                            onChange={(e) =>
+                                changeOCRMode(
+                                    e.target.value as Configuration['ocr_mode']
+                                )
+                            }
+                        >
+                            <option value="cursor">
+                                Word under the mouse pointer
+                            </option>
+                            <option value="region">
+                                Text in a region selected on screen
+                            </option>
+                        </select>
+                    </div>
                     <div class="input-group mb-3">
                         <span class="input-group-text">Width</span>
                         <input
@@ -240,17 +682,165 @@ const Settings: Component = () => {
                             }}
                         />
                     </div>
+                    <For
+                        each={
+                            [
+                                ['ocr_detection_model', 'Detection model'],
+                                ['ocr_recognition_model', 'Recognition model'],
+                            ] as const
+                        }
+                    >
+                        {([key, label]) => (
+                            <div class="input-group mb-3">
+                                <span class="input-group-text">{label}</span>
+                                <input
+                                    type="text"
+                                    class="form-control"
+                                    placeholder="Bundled model"
+                                    value={appConfig[key]}
+                                    onChange={(e) =>
+                                        changeOCRModel(key, e.target.value)
+                                    }
+                                />
+                                <button
+                                    class="btn btn-outline-secondary"
+                                    onClick={() => chooseOCRModel(key)}
+                                >
+                                    Choose
+                                </button>
+                                <button
+                                    class="btn btn-outline-secondary"
+                                    title="Use the bundled model"
+                                    onClick={() => changeOCRModel(key, '')}
+                                >
+                                    <i class="bi bi-arrow-counterclockwise"></i>
+                                </button>
+                            </div>
+                        )}
+                    </For>
+                    <For
+                        each={
+                            [
+                                ['ocr_grayscale', 'Convert to grayscale'],
+                                [
+                                    'ocr_auto_invert',
+                                    'Invert light text on dark background',
+                                ],
+                                ['ocr_upscale', 'Enlarge small text'],
+                                ['ocr_normalize_contrast', 'Normalize contrast'],
+                                [
+                                    'ocr_dpi_aware_crop',
+                                    'Scale width and height by screen scale factor',
+                                ],
+                                [
+                                    'ocr_debug',
+                                    'Save captures to the log folder for debugging',
+                                ],
+                            ] as const
+                        }
+                    >
+                        {([key, label]) => (
+                            <div class="form-check mb-2">
+                                <input
+                                    class="form-check-input"
+                                    type="checkbox"
+                                    checked={appConfig[key]}
+                                    id={key}
+                                    onChange={() => toggleOCROption(key)}
+                                />
+                                <label class="form-check-label" for={key}>
+                                    {label}
+                                </label>
+                            </div>
+                        )}
+                    </For>
+                    <div class="form-text">
+                        <Switch fallback="OCR engine is loading">
+                            <Match when={ocrStatus()?.loaded}>
+                                <i class="bi bi-check-circle text-success me-1"></i>
+                                OCR engine is ready
+                            </Match>
+                            <Match when={ocrStatus()?.error}>
+                                {(err) => (
+                                    <span class="text-danger">
+                                        <i class="bi bi-exclamation-circle me-1"></i>
+                                        OCR is disabled. {err()}
+                                    </span>
+                                )}
+                            </Match>
+                        </Switch>
+                    </div>
+                </div>
+                <div class="mt-3">
+                    <h6 class="form-label">Clipboard</h6>
+                    <div class="form-check mb-3">
+                        <input
+                            class="form-check-input"
+                            type="checkbox"
+                            checked={appConfig.clipboard_watch}
+                            id="clipboard-watch"
+                            onChange={toggleClipboardWatch}
+                        />
+                        <label class="form-check-label" for="clipboard-watch">
+                            Look up text as it's copied
+                        </label>
+                    </div>
                     <div class="input-group mb-3">
-                        <span class="input-group-text">Shortcut</span>
+                        <span class="input-group-text">Max length</span>
                         <input
-                            type="text"
+                            type="number"
                             class="form-control"
-                            value={appConfig.ocr_shortcut}
+                            value={appConfig.clipboard_max_length}
+                            min={0}
                             onChange={(e) => {
-                                changeOCRShortcut(e.target.value);
+                                changeClipboardMaxLength(+e.target.value);
                             }}
                         />
                     </div>
+                    <div>
+                        <For
+                            each={
+                                [
+                                    'latin',
+                                    'greek',
+                                    'cyrillic',
+                                    'hebrew',
+                                    'arabic',
+                                    'thai',
+                                    'hangul',
+                                    'kana',
+                                    'han',
+                                ] as const
+                            }
+                        >
+                            {(script) => (
+                                <div class="form-check form-check-inline">
+                                    <input
+                                        class="form-check-input"
+                                        type="checkbox"
+                                        checked={appConfig.clipboard_scripts.includes(
+                                            script
+                                        )}
+                                        id={`script-${script}`}
+                                        onChange={() =>
+                                            toggleClipboardScript(script)
+                                        }
+                                    />
+                                    <label
+                                        class="form-check-label text-capitalize"
+                                        for={`script-${script}`}
+                                    >
+                                        {script}
+                                    </label>
+                                </div>
+                            )}
+                        </For>
+                    </div>
+                    <div class="form-text">
+                        Copied text is looked up only if its letters are in
+                        the checked scripts, any script if none is checked.
+                        Max length 0 is no limit.
+                    </div>
                 </div>
                 <div class="mt-3">
                     <h6 class="form-label">Node Cache</h6>
@@ -264,6 +854,75 @@ const Settings: Component = () => {
                         <span class="input-group-text">M</span>
                     </div>
                 </div>
+                <div class="mt-3">
+                    <h6 class="form-label">HTTP Server</h6>
+                    <span class="fst-italic fw-lighter">
+                        Serves entries to this app and other programs. The
+                        port is written to server.json in the app data folder.
+                    </span>
+                    <div class="input-group">
+                        <span class="input-group-text">Port</span>
+                        <input
+                            type="number"
+                            class="form-control"
+                            value={appConfig.http_port}
+                            placeholder="Auto"
+                            min={0}
+                            max={65535}
+                            onChange={(e) => {
+                                changeHttpPort(+e.target.value);
+                            }}
+                        />
+                    </div>
+                    <div class="form-text mb-3">
+                        0 for the first available port from 19000.{' '}
+                        <Switch>
+                            <Match when={serverStatus()?.error}>
+                                {(err) => (
+                                    <span class="text-danger">
+                                        <i class="bi bi-exclamation-circle me-1"></i>
+                                        {err()}
+                                    </span>
+                                )}
+                            </Match>
+                            <Match when={serverStatus()?.port}>
+                                {(port) => <span>Listening on {port()}</span>}
+                            </Match>
+                        </Switch>
+                    </div>
+                </div>
+                <div class="mt-3">
+                    <h6 class="form-label">DICT Server</h6>
+                    <span class="fst-italic fw-lighter">
+                        Serve dictionaries to DICT protocol (RFC 2229) clients
+                        on localhost
+                    </span>
+                    <div class="form-check mb-2">
+                        <input
+                            class="form-check-input"
+                            type="checkbox"
+                            checked={appConfig.dict_server}
+                            id="dict-server"
+                            onChange={toggleDictServer}
+                        />
+                        <label class="form-check-label" for="dict-server">
+                            Enable DICT Server
+                        </label>
+                    </div>
+                    <div class="input-group mb-3">
+                        <span class="input-group-text">Port</span>
+                        <input
+                            type="number"
+                            class="form-control"
+                            value={appConfig.dict_server_port}
+                            min={1}
+                            max={65535}
+                            onChange={(e) => {
+                                changeDictServerPort(+e.target.value);
+                            }}
+                        />
+                    </div>
+                </div>
                 <div class="mt-3">
                     <h6 class="form-label">Developer</h6>
                     <p>
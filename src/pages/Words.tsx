@@ -8,7 +8,8 @@ import {
     onMount,
 } from 'solid-js';
 import BackPage from '../components/BackPage';
-import { sendMessage } from '../base';
+import { playPronunciation, sendMessage } from '../base';
+import poptip from 'poptip';
 import PopupWord from '../components/PopupWord';
 import { createStore } from 'solid-js/store';
 import { useParams } from '@solidjs/router';
@@ -82,6 +83,16 @@ const Words: Component = () => {
         );
     }
 
+    async function play(wd: WordModel) {
+        try {
+            if (!(await playPronunciation(wd.name))) {
+                poptip.info('No pronunciation found');
+            }
+        } catch (e) {
+            poptip.error(`fail to play pronunciation.\n${e}`);
+        }
+    }
+
     async function deleteWord(wd: WordModel) {
         if (wd === null) {
             return;
@@ -158,13 +169,26 @@ const Words: Component = () => {
                                                     word={item}
                                                 ></FamiliarFlag>
                                                 {item.name}
+                                                <Show when={item.context}>
+                                                    <div class="small text-body-secondary">
+                                                        {item.context}
+                                                    </div>
+                                                </Show>
                                             </span>
                                             <div
-                                                class="dropdown"
+                                                class="d-flex dropdown"
                                                 onClick={(e) =>
                                                     e.stopPropagation()
                                                 }
                                             >
+                                                <button
+                                                    type="button"
+                                                    class="btn btn-ghost"
+                                                    title="Play pronunciation"
+                                                    onClick={() => play(item)}
+                                                >
+                                                    <i class="bi bi-volume-up"></i>
+                                                </button>
                                                 <button
                                                     type="button"
                                                     class="btn btn-ghost"
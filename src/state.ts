@@ -4,15 +4,47 @@ import { createStore } from 'solid-js/store';
 export const [appConfig, setAppConfig] = createStore<Configuration>({
     dict_dir: '',
     dicts: [],
+    dict_groups: [],
     cache_size: 100,
     win_width: 0,
     win_height: 0,
     ocr_width: 0,
     ocr_height: 0,
-    ocr_shortcut: '',
+    shortcuts: {
+        ocr: '',
+        clipboard: '',
+        toggle_window: '',
+        quick_search: '',
+    },
+    ocr_shortcut_enabled: true,
+    ocr_mode: 'cursor',
+    ocr_detection_model: '',
+    ocr_recognition_model: '',
+    ocr_grayscale: true,
+    ocr_auto_invert: true,
+    ocr_upscale: true,
+    ocr_normalize_contrast: true,
+    ocr_dpi_aware_crop: true,
+    ocr_debug: false,
+    clipboard_watch: false,
+    clipboard_max_length: 50,
+    clipboard_scripts: [],
+    popup_lookup: false,
     prefix_limit: 5,
     phrase_limit: 10,
     dev_mode: false,
+    dark_mode: false,
+    font_scale: 100,
+    sanitize_entries: false,
+    dict_server: false,
+    http_port: 0,
+    dict_server_port: 2628,
 });
 
 export const [serverPort, setServerPort] = createSignal(0);
+
+/** Text recognized in a region of the screen, to pick words from */
+export const [ocrResult, setOcrResult] = createSignal<OcrResult | null>(null);
+
+/** Set to focus the search box once the home page is shown */
+export const [focusSearch, setFocusSearch] = createSignal(false);
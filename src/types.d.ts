@@ -2,19 +2,78 @@ interface DictItem {
     id: number;
     name: string;
     available: boolean;
+    trusted: boolean;
 }
+/** A named set of dictionaries, only they are available when it's used */
+interface DictGroup {
+    name: string;
+    dicts: string[];
+}
+
+type ShortcutAction = 'ocr' | 'clipboard' | 'toggle_window' | 'quick_search';
+
+/** A registered shortcut, with the error if it fails to register */
+interface ShortcutStatus {
+    action: ShortcutAction;
+    shortcut: string;
+    error: string | null;
+}
+
+type ClipboardScript =
+    | 'latin'
+    | 'greek'
+    | 'cyrillic'
+    | 'hebrew'
+    | 'arabic'
+    | 'thai'
+    | 'hangul'
+    | 'kana'
+    | 'han';
+
 interface Configuration {
     dict_dir: string;
     dicts: DictItem[];
+    dict_groups: DictGroup[];
     cache_size: number;
     win_width: number;
     win_height: number;
     ocr_width: number;
     ocr_height: number;
-    ocr_shortcut: string;
+    shortcuts: Record<ShortcutAction, string>;
+    ocr_shortcut_enabled: boolean;
+    ocr_mode: 'cursor' | 'region';
+    ocr_detection_model: string;
+    ocr_recognition_model: string;
+    ocr_grayscale: boolean;
+    ocr_auto_invert: boolean;
+    ocr_upscale: boolean;
+    ocr_normalize_contrast: boolean;
+    ocr_dpi_aware_crop: boolean;
+    ocr_debug: boolean;
+    clipboard_watch: boolean;
+    clipboard_max_length: number;
+    clipboard_scripts: ClipboardScript[];
+    popup_lookup: boolean;
     prefix_limit: number;
     phrase_limit: number;
     dev_mode: boolean;
+    dark_mode: boolean;
+    font_scale: number;
+    sanitize_entries: boolean;
+    http_port: number;
+    dict_server: boolean;
+    dict_server_port: number;
+}
+
+/** The port of the HTTP server, or why it fails to start */
+interface ServerStatus {
+    port: number;
+    error: string | null;
+}
+
+interface Entry {
+    name: string;
+    content: string;
 }
 
 interface BookModel {
@@ -29,6 +88,8 @@ interface WordModel {
     familiar: number;
     book_id: number;
     create_time: number;
+    /** The sentence the word was looked up in */
+    context: string;
 }
 
 type Pagination<T> = {
@@ -47,6 +108,8 @@ type IpcMessage = {
     platform: RR<void, OperationSystem>;
     open_devtools: RR<void, void>;
     get_server_port: RR<void, number>;
+    get_server_status: RR<void, ServerStatus>;
+    get_theme_dir: RR<void, string>;
     search: RR<
         {
             id: number;
@@ -57,9 +120,18 @@ type IpcMessage = {
         },
         string[]
     >;
+    get_entry_text: RR<{ id: number; name: string }, string | null>;
+    search_entries: RR<{ id: number; name: string }, Entry[]>;
+    get_pronunciations: RR<{ name: string; id?: number }, Pronunciation[]>;
     resize_cache: RR<number, void>;
     get_settings: RR<void, Configuration>;
-    set_settings: RR<Partial<Configuration>, void>;
+    set_settings: RR<
+        Partial<Configuration> & {
+            /** Switches to the dictionary group of this name */
+            dict_group?: string;
+        },
+        void
+    >;
     reload_dicts: RR<void, void>;
     get_book_list: RR<void, BookModel[]>;
     add_book: RR<string, BookModel>;
@@ -71,11 +143,85 @@ type IpcMessage = {
         { book_id: number; page: number; size: number; order?: string },
         Pagination<WordModel>
     >;
-    add_word: RR<[number, string], void>;
+    add_word: RR<
+        { book_id: number; name: string; context: string | null },
+        void
+    >;
     delete_words: RR<number[], void>;
     set_word_familiar: RR<{ id: number; familiar: number }, void>;
+    ocr_region: RR<
+        {
+            x: number;
+            y: number;
+            width: number;
+            height: number;
+            view_width: number;
+            view_height: number;
+        },
+        void
+    >;
+    close_ocr_overlay: RR<void, void>;
+    close_popup: RR<void, void>;
+    get_ocr_status: RR<void, OcrStatus>;
+    get_shortcut_status: RR<void, ShortcutStatus[]>;
+    ocr_image_file: RR<string, OcrResult>;
+    ocr_clipboard_image: RR<void, OcrResult>;
 };
 
+interface OcrWord {
+    text: string;
+    x: number;
+    y: number;
+    width: number;
+    height: number;
+}
+
+interface OcrLine {
+    text: string;
+    words: OcrWord[];
+}
+
+/** Neither loaded nor failed while the models are being loaded */
+interface OcrStatus {
+    loaded: boolean;
+    error: string | null;
+}
+
+interface OcrHit {
+    line: number;
+    word: number;
+    /** The word without punctuation, joined if hyphenated across lines */
+    text: string;
+}
+
+interface OcrResult {
+    text: string;
+    lines: OcrLine[];
+    /** The word under the mouse pointer in cursor mode */
+    hit: OcrHit | null;
+}
+
+interface Pronunciation {
+    dict_id: number;
+    dict: string;
+    name: string;
+    url: string;
+}
+
+/** A word to look up, with the name of the dictionary to show first */
+interface LookupRequest {
+    text: string;
+    dict: string | null;
+}
+
+interface EntryViewed {
+    dict_id: number;
+    name: string;
+    query: string;
+    nav: 'link' | 'history' | null;
+    view: 'popup' | null;
+}
+
 interface ChildMessage {
     type: 'resource' | 'entry';
     name: string;